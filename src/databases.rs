@@ -1,6 +1,62 @@
-use rocket_db_pools::deadpool_redis::Pool;
+use anyhow::anyhow;
+use rocket::fairing::AdHoc;
+use rocket_db_pools::deadpool_redis::redis::aio::{MultiplexedConnection, PubSub};
+use rocket_db_pools::deadpool_redis::redis::Client;
+use rocket_db_pools::deadpool_redis::{Connection, Pool};
 use rocket_db_pools::Database;
+use state::InitCell;
 
 #[derive(Database)]
 #[database("tasks")]
 pub struct Tasks(Pool);
+
+static POOL: InitCell<Pool> = InitCell::new();
+static CLIENT: InitCell<Client> = InitCell::new();
+
+impl Tasks {
+    pub fn register() -> AdHoc {
+        AdHoc::try_on_ignite("Tasks Pool", |rocket| async {
+            if let Some(tasks) = Tasks::fetch(&rocket) {
+                POOL.set(tasks.0.clone());
+            }
+            let client = rocket
+                .figment()
+                .extract_inner::<String>("databases.tasks.url")
+                .map_err(|err| anyhow!(err))
+                .and_then(|url| Ok(Client::open(url)?));
+            match client {
+                Ok(client) => {
+                    CLIENT.set(client);
+                    Ok(rocket)
+                }
+                Err(err) => {
+                    eprintln!("Failed to open database 'tasks': {:?}", err);
+                    Err(rocket)
+                }
+            }
+        })
+    }
+
+    pub async fn connect() -> anyhow::Result<Connection> {
+        let pool = POOL
+            .try_get()
+            .ok_or_else(|| anyhow!("Database 'tasks' not initialized"))?;
+        Ok(pool.get().await?)
+    }
+
+    pub async fn dedicated() -> anyhow::Result<MultiplexedConnection> {
+        let client = CLIENT
+            .try_get()
+            .ok_or_else(|| anyhow!("Database 'tasks' not initialized"))?;
+        Ok(client.get_multiplexed_async_connection().await?)
+    }
+
+    pub async fn psubscribe(pattern: &str) -> anyhow::Result<PubSub> {
+        let client = CLIENT
+            .try_get()
+            .ok_or_else(|| anyhow!("Database 'tasks' not initialized"))?;
+        let mut pubsub = client.get_async_pubsub().await?;
+        pubsub.psubscribe(pattern).await?;
+        Ok(pubsub)
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::anyhow;
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome},
+    Request, State,
+};
+
+use crate::entities::config::Config;
+
+pub struct Admin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = anyhow::Error;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let config = match request.guard::<&State<Config>>().await {
+            Outcome::Success(config) => config,
+            _ => {
+                return Outcome::Error((
+                    Status::InternalServerError,
+                    anyhow!("State 'Config' not existed"),
+                ));
+            }
+        };
+        // Admin endpoints stay closed until a token is configured.
+        let Some(token) = config
+            .services
+            .admin
+            .token
+            .as_deref()
+            .filter(|token| !token.is_empty())
+        else {
+            return Outcome::Error((Status::Forbidden, anyhow!("Admin token not configured")));
+        };
+        let provided = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match provided {
+            Some(provided) if equals(provided.as_bytes(), token.as_bytes()) => {
+                Outcome::Success(Admin)
+            }
+            _ => Outcome::Error((Status::Unauthorized, anyhow!("Invalid admin token"))),
+        }
+    }
+}
+
+fn equals(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
@@ -2,6 +2,8 @@ use std::collections::HashMap;
 
 use serde::Deserialize;
 
+use crate::entities::request::GenerationOptions;
+
 #[derive(Deserialize, Clone)]
 pub struct Config {
     pub services: ServiceConfig,
@@ -10,8 +12,12 @@ pub struct Config {
 #[derive(Deserialize, Clone)]
 pub struct ServiceConfig {
     pub models: HashMap<String, ModelConfig>,
+    pub routing: RoutingConfig,
     pub executor: ExecutorConfig,
     pub oss: OSSConfig,
+    pub webhook: WebhookConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
 }
 
 #[derive(Deserialize, Clone)]
@@ -19,6 +25,21 @@ pub struct ModelConfig {
     pub model: String,
     pub base_url: String,
     pub api_key: String,
+    #[serde(default)]
+    pub defaults: GenerationOptions,
+    #[serde(default)]
+    pub limits: ModelLimits,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct ModelLimits {
+    pub max_tokens: Option<u32>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RoutingConfig {
+    pub text: String,
+    pub media: String,
 }
 
 #[derive(Deserialize, Clone)]
@@ -26,13 +47,71 @@ pub struct ExecutorConfig {
     pub num_workers: usize,
     pub timeout: u64,
     pub expiration: u64,
+    pub heartbeat: u64,
+    pub max_requeues: u32,
+    pub max_retries: u32,
+    pub retry_backoff: u64,
+    pub max_backoff: u64,
+    pub starvation_interval: u64,
+    pub grace_period: u64,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct RetentionConfig {
+    pub pending: Option<u64>,
+    pub running: Option<u64>,
+    pub finished: Option<u64>,
+    pub failed: Option<u64>,
+    pub cancelled: Option<u64>,
+    #[serde(default)]
+    pub archive: bool,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct WebhookConfig {
+    pub timeout: u64,
+    pub max_retries: u32,
+    #[serde(default)]
+    pub allow_private: bool,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct AdminConfig {
+    pub token: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Aliyun,
+    Local,
+    S3,
 }
 
 #[derive(Deserialize, Clone)]
 pub struct OSSConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
     pub prefix: String,
+    #[serde(default)]
     pub bucket: String,
+    #[serde(default)]
     pub endpoint: String,
+    #[serde(default)]
+    pub public_endpoint: Option<String>,
+    #[serde(default)]
     pub access_key_id: String,
+    #[serde(default)]
     pub access_key_secret: String,
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub path_style: bool,
+    pub max_presign_expires: u64,
+    pub max_upload_age: u64,
+    #[serde(default)]
+    pub root: Option<String>,
 }
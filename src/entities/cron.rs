@@ -0,0 +1,47 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::entities::{datetime::DateTime, request::ChatRequest, task::Priority};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CronRequest {
+    #[serde(flatten)]
+    pub chat: ChatRequest,
+    pub cron: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub submitter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Cron {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub name: Option<String>,
+    pub cron: String,
+    pub template: ChatRequest,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub submitter: Option<String>,
+    pub enabled: bool,
+    pub next_run: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub last_run: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub last_task_id: Option<String>,
+    pub create_time: DateTime<Local>,
+}
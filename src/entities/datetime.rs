@@ -46,6 +46,12 @@ where
     }
 }
 
+impl<T: TimeZone> From<chrono::DateTime<T>> for DateTime<T> {
+    fn from(datetime: chrono::DateTime<T>) -> Self {
+        Self(datetime)
+    }
+}
+
 impl<T: TimeZone> Deref for DateTime<T> {
     type Target = chrono::DateTime<T>;
 
@@ -1,6 +1,11 @@
+pub mod admin;
 pub mod config;
+pub mod cron;
 pub mod datetime;
+pub mod event;
 pub mod message;
+pub mod openai;
 pub mod oss;
+pub mod request;
 pub mod response;
 pub mod task;
@@ -0,0 +1,256 @@
+use agentx::{Prompt, Role};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::entities::{
+    message::{Message, Video},
+    request::GenerationOptions,
+};
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Stop {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Url {
+    pub url: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: Url },
+    VideoUrl { video_url: Url },
+    Video { video: Vec<String> },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Content {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ChatMessage {
+    pub role: Role,
+    #[serde(default)]
+    pub content: Option<Content>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ChatCompletionRequest {
+    #[serde(default)]
+    pub model: Option<String>,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub max_completion_tokens: Option<u32>,
+    #[serde(default)]
+    pub stop: Option<Stop>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl ChatCompletionRequest {
+    pub fn prompt(&self) -> Prompt {
+        self.messages
+            .iter()
+            .cloned()
+            .map(|message| agentx::Message::from(Message::from(message)))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    pub fn options(&self) -> GenerationOptions {
+        GenerationOptions {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_completion_tokens.or(self.max_tokens),
+            stop: self.stop.clone().map(|stop| match stop {
+                Stop::One(stop) => vec![stop],
+                Stop::Many(stop) => stop,
+            }),
+            seed: self.seed,
+        }
+    }
+}
+
+impl From<ChatMessage> for Message {
+    fn from(message: ChatMessage) -> Self {
+        let ChatMessage { role, content } = message;
+        let mut text = None;
+        let mut images = Vec::new();
+        let mut videos = Vec::new();
+        match content {
+            Some(Content::Text(content)) => text = Some(content),
+            Some(Content::Parts(parts)) => {
+                let mut texts = Vec::new();
+                for part in parts {
+                    match part {
+                        ContentPart::Text { text } => texts.push(text),
+                        ContentPart::ImageUrl { image_url } => images.push(image_url.url),
+                        ContentPart::VideoUrl { video_url } => {
+                            videos.push(Video::Url(video_url.url))
+                        }
+                        ContentPart::Video { video } => videos.push(Video::Images(video)),
+                    }
+                }
+                if !texts.is_empty() {
+                    text = Some(texts.join("\n"));
+                }
+            }
+            None => (),
+        }
+        Message {
+            role: Some(role),
+            text,
+            images: (!images.is_empty()).then_some(images),
+            videos: (!videos.is_empty()).then_some(videos),
+            context: None,
+        }
+    }
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct ChoiceMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Choice {
+    pub index: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<ChoiceMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta: Option<ChoiceMessage>,
+    pub finish_reason: Option<&'static str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChatCompletion {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<Choice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl Usage {
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let field = |names: &[&str]| names.iter().find_map(|name| value.get(name)?.as_u64());
+        let prompt_tokens = field(&["prompt_tokens", "input_tokens"])?;
+        let completion_tokens = field(&["completion_tokens", "output_tokens"])?;
+        let total_tokens = field(&["total_tokens"]).unwrap_or(prompt_tokens + completion_tokens);
+        Some(Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens,
+        })
+    }
+
+    pub fn finish_reason(usage: Option<&Self>, max_tokens: Option<u32>) -> &'static str {
+        // agentx does not report the upstream finish reason, so a cutoff is inferred from the
+        // token budget. Tool calls are never requested through this API.
+        match (usage, max_tokens) {
+            (Some(usage), Some(max_tokens)) if usage.completion_tokens >= max_tokens as u64 => {
+                "length"
+            }
+            _ => "stop",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ModelObject {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub owned_by: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ModelList {
+    pub object: &'static str,
+    pub data: Vec<ModelObject>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ErrorObject {
+    pub message: String,
+    pub r#type: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ErrorResponse {
+    pub error: ErrorObject,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_de_chat_completion_request() {
+        let json = r#"{
+            "model": "qwen3-vl",
+            "messages": [
+                {"role": "system", "content": "You are a helpful assistant."},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "这是什么"},
+                    {"type": "image_url", "image_url": {"url": "https://www.baidu.com/img/bd_logo.png"}}
+                ]}
+            ],
+            "stop": "\n",
+            "max_tokens": 128
+        }"#;
+        let request: ChatCompletionRequest = serde_json::from_str(json).unwrap();
+        let options = request.options();
+        assert_eq!(options.max_tokens, Some(128));
+        assert_eq!(options.stop, Some(vec!["\n".to_owned()]));
+        let message: Message = request.messages[1].clone().into();
+        assert_eq!(message.text.as_deref(), Some("这是什么"));
+        assert_eq!(message.images.map(|images| images.len()), Some(1));
+        let prompt = request.prompt();
+        assert!(prompt.is_media());
+    }
+
+    #[test]
+    fn test_usage() {
+        let usage = Usage::from_value(&serde_json::json!({
+            "input_tokens": 10,
+            "output_tokens": 128,
+        }))
+        .unwrap();
+        assert_eq!(usage.total_tokens, 138);
+        assert_eq!(Usage::finish_reason(Some(&usage), Some(128)), "length");
+        assert_eq!(Usage::finish_reason(Some(&usage), Some(256)), "stop");
+        assert_eq!(Usage::finish_reason(None, Some(128)), "stop");
+        assert!(Usage::from_value(&serde_json::json!({})).is_none());
+    }
+}
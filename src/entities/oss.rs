@@ -1,16 +1,43 @@
 use std::str::FromStr;
 
 use anyhow::anyhow;
+use chrono::{Local, Utc};
 use rocket::{
     http::{ContentType, Status},
     request::{FromRequest, Outcome},
     Request,
 };
+use serde::Serialize;
 
-#[derive(Debug)]
+use crate::entities::datetime::DateTime;
+
+#[derive(Serialize, Debug)]
+pub struct ObjectSummary {
+    pub name: String,
+    pub size: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MultipartUpload {
+    pub name: String,
+    pub upload_id: String,
+    pub initiated: DateTime<Local>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PresignedUrl {
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    pub expire_time: DateTime<Local>,
+}
+
+#[derive(Debug, Default)]
 pub struct ObjectMeta {
     pub content_type: String,
     pub content_length: u64,
+    pub e_tag: Option<String>,
+    pub last_modified: Option<chrono::DateTime<Utc>>,
 }
 
 impl ObjectMeta {
@@ -72,6 +99,209 @@ impl<'r> FromRequest<'r> for ObjectMeta {
         Outcome::Success(ObjectMeta {
             content_type,
             content_length,
+            ..Default::default()
         })
     }
 }
+
+static MAX_RANGES: usize = 16;
+
+pub fn format_http_date(datetime: &chrono::DateTime<Utc>) -> String {
+    datetime.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+pub fn parse_http_date(s: &str) -> Option<chrono::DateTime<Utc>> {
+    chrono::DateTime::parse_from_rfc2822(s.trim())
+        .ok()
+        .map(|datetime| datetime.with_timezone(&Utc))
+}
+
+fn e_tag_matches(tag: &str, e_tag: &str, weak: bool) -> bool {
+    if weak {
+        tag.trim_start_matches("W/") == e_tag.trim_start_matches("W/")
+    } else {
+        !tag.starts_with("W/") && tag == e_tag
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ByteRange {
+    Full,
+    Partial(Vec<(u64, u64)>),
+    Unsatisfiable,
+}
+
+#[derive(Debug, Default)]
+pub struct Preconditions {
+    pub range: Option<String>,
+    pub if_range: Option<String>,
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+}
+
+impl Preconditions {
+    pub fn is_not_modified(&self, meta: &ObjectMeta) -> bool {
+        if let Some(if_none_match) = &self.if_none_match {
+            return if_none_match.split(',').map(str::trim).any(|tag| {
+                tag == "*"
+                    || meta
+                        .e_tag
+                        .as_ref()
+                        .is_some_and(|e_tag| e_tag_matches(tag, e_tag, true))
+            });
+        }
+        match (&self.if_modified_since, meta.last_modified) {
+            (Some(since), Some(last_modified)) => {
+                parse_http_date(since).is_some_and(|since| last_modified <= since)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn byte_range(&self, meta: &ObjectMeta) -> ByteRange {
+        let Some(range) = &self.range else {
+            return ByteRange::Full;
+        };
+        if let Some(if_range) = &self.if_range {
+            let matched = if if_range.trim().starts_with('"') || if_range.starts_with("W/") {
+                meta.e_tag
+                    .as_ref()
+                    .is_some_and(|e_tag| e_tag_matches(if_range.trim(), e_tag, false))
+            } else {
+                meta.last_modified
+                    .is_some_and(|last_modified| parse_http_date(if_range) == Some(last_modified))
+            };
+            if !matched {
+                return ByteRange::Full;
+            }
+        }
+        let Some(spec) = range.trim().strip_prefix("bytes=") else {
+            return ByteRange::Full;
+        };
+        let length = meta.content_length;
+        let mut ranges = Vec::new();
+        for part in spec.split(',') {
+            let Some((start, end)) = part.trim().split_once('-') else {
+                return ByteRange::Full;
+            };
+            if start.is_empty() {
+                let Ok(suffix) = end.parse::<u64>() else {
+                    return ByteRange::Full;
+                };
+                if suffix > 0 && length > 0 {
+                    ranges.push((length.saturating_sub(suffix), length - 1));
+                }
+            } else {
+                let Ok(start) = start.parse::<u64>() else {
+                    return ByteRange::Full;
+                };
+                let end = if end.is_empty() {
+                    u64::MAX
+                } else {
+                    match end.parse::<u64>() {
+                        Ok(end) if end >= start => end,
+                        _ => return ByteRange::Full,
+                    }
+                };
+                if start < length {
+                    ranges.push((start, end.min(length - 1)));
+                }
+            }
+        }
+        if ranges.len() > MAX_RANGES {
+            ByteRange::Full
+        } else if ranges.is_empty() {
+            ByteRange::Unsatisfiable
+        } else {
+            ByteRange::Partial(ranges)
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Preconditions {
+    type Error = anyhow::Error;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+        let header = |name: &str| headers.get_one(name).map(ToOwned::to_owned);
+        Outcome::Success(Preconditions {
+            range: header("Range"),
+            if_range: header("If-Range"),
+            if_none_match: header("If-None-Match"),
+            if_modified_since: header("If-Modified-Since"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_meta() -> ObjectMeta {
+        ObjectMeta {
+            content_type: "application/pdf".to_owned(),
+            content_length: 1000,
+            e_tag: Some("\"abc\"".to_owned()),
+            last_modified: parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT"),
+        }
+    }
+
+    fn with_range(range: &str) -> Preconditions {
+        Preconditions {
+            range: Some(range.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_byte_range() {
+        let meta = build_meta();
+        assert_eq!(Preconditions::default().byte_range(&meta), ByteRange::Full);
+        assert_eq!(
+            with_range("bytes=0-499").byte_range(&meta),
+            ByteRange::Partial(vec![(0, 499)])
+        );
+        assert_eq!(
+            with_range("bytes=900-, -50, 100-2000").byte_range(&meta),
+            ByteRange::Partial(vec![(900, 999), (950, 999), (100, 999)])
+        );
+        assert_eq!(
+            with_range("bytes=1000-").byte_range(&meta),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(with_range("bytes=5-1").byte_range(&meta), ByteRange::Full);
+        assert_eq!(with_range("items=0-1").byte_range(&meta), ByteRange::Full);
+        let preconditions = Preconditions {
+            if_range: Some("\"stale\"".to_owned()),
+            ..with_range("bytes=0-1")
+        };
+        assert_eq!(preconditions.byte_range(&meta), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_not_modified() {
+        let meta = build_meta();
+        let preconditions = Preconditions {
+            if_none_match: Some("W/\"abc\", \"def\"".to_owned()),
+            ..Default::default()
+        };
+        assert!(preconditions.is_not_modified(&meta));
+        let preconditions = Preconditions {
+            if_none_match: Some("\"def\"".to_owned()),
+            if_modified_since: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_owned()),
+            ..Default::default()
+        };
+        assert!(!preconditions.is_not_modified(&meta));
+        let preconditions = Preconditions {
+            if_modified_since: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_owned()),
+            ..Default::default()
+        };
+        assert!(preconditions.is_not_modified(&meta));
+        let preconditions = Preconditions {
+            if_modified_since: Some("Tue, 20 Oct 2015 07:28:00 GMT".to_owned()),
+            ..Default::default()
+        };
+        assert!(!preconditions.is_not_modified(&meta));
+    }
+}
@@ -0,0 +1,101 @@
+use agentx::{ModelOptions, OpenAIModelOptions};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::entities::{datetime::DateTime, message::Message, task::Priority};
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct GenerationOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl GenerationOptions {
+    pub fn or(self, defaults: &GenerationOptions) -> Self {
+        Self {
+            temperature: self.temperature.or(defaults.temperature),
+            top_p: self.top_p.or(defaults.top_p),
+            max_tokens: self.max_tokens.or(defaults.max_tokens),
+            stop: self.stop.or_else(|| defaults.stop.clone()),
+            seed: self.seed.or(defaults.seed),
+        }
+    }
+}
+
+impl From<GenerationOptions> for ModelOptions {
+    fn from(options: GenerationOptions) -> Self {
+        let GenerationOptions {
+            temperature,
+            top_p,
+            max_tokens,
+            stop,
+            seed,
+        } = options;
+        let mut options = OpenAIModelOptions::new();
+        if let Some(temperature) = temperature {
+            options = options.temperature(temperature);
+        }
+        if let Some(top_p) = top_p {
+            options = options.top_p(top_p);
+        }
+        if let Some(max_tokens) = max_tokens {
+            options = options.max_tokens(max_tokens);
+        }
+        if let Some(stop) = stop {
+            options = options.stop(stop);
+        }
+        if let Some(seed) = seed {
+            options = options.seed(seed);
+        }
+        options.into()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChatRequest {
+    #[serde(flatten)]
+    pub message: Message,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub options: Option<GenerationOptions>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TaskRequest {
+    #[serde(flatten)]
+    pub chat: ChatRequest,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub callback_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub callback_secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub submitter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub run_at: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub delay_secs: Option<u64>,
+}
@@ -1,18 +1,48 @@
 use std::{fmt::Display, future::Future};
 
-use rocket::{http::Status, response::status};
+use rocket::{
+    http::Status,
+    response::{self, status, Responder},
+    serde::json::Json,
+    Request,
+};
 use serde::Serialize;
 
 #[derive(Serialize)]
 pub struct Response<T> {
+    #[serde(skip)]
+    status: Status,
     success: bool,
     msg: String,
     data: Option<T>,
 }
 
+#[derive(Debug)]
+pub struct BadRequest(pub String);
+
+impl Display for BadRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for BadRequest {}
+
+#[derive(Debug)]
+pub struct NotFound(pub String);
+
+impl Display for NotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NotFound {}
+
 impl<T> Response<T> {
     pub fn ok(data: T) -> Self {
         Self {
+            status: Status::Ok,
             success: true,
             msg: "成功".to_string(),
             data: Some(data),
@@ -21,6 +51,7 @@ impl<T> Response<T> {
 
     pub fn error<M: Display>(msg: M) -> Self {
         Self {
+            status: Status::Ok,
             success: false,
             msg: msg.to_string(),
             data: None,
@@ -33,8 +64,27 @@ impl<T> Response<T> {
     {
         match future.await {
             Ok(data) => Self::ok(data),
-            Err(err) => Self::error(format!("{:#}", err)),
+            Err(err) => err.into(),
+        }
+    }
+}
+
+impl<T> From<anyhow::Error> for Response<T> {
+    fn from(err: anyhow::Error) -> Self {
+        let mut response = Self::error(format!("{:#}", err));
+        if err.downcast_ref::<BadRequest>().is_some() {
+            response.status = Status::BadRequest;
+        } else if err.downcast_ref::<NotFound>().is_some() {
+            response.status = Status::NotFound;
         }
+        response
+    }
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for Response<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = self.status;
+        status::Custom(status, Json(self)).respond_to(request)
     }
 }
 
@@ -44,7 +94,12 @@ impl<T> From<Response<T>> for Result<T, status::Custom<String>> {
             Ok(response.data.unwrap())
         } else {
             eprintln!("Invoke error: {}", response.msg);
-            Err(status::Custom(Status::InternalServerError, response.msg))
+            let status = if response.status == Status::Ok {
+                Status::InternalServerError
+            } else {
+                response.status
+            };
+            Err(status::Custom(status, response.msg))
         }
     }
 }
@@ -1,16 +1,72 @@
-use crate::entities::{datetime::DateTime, message::Message};
+use crate::entities::{
+    datetime::DateTime,
+    request::{ChatRequest, GenerationOptions},
+};
 use agentx::{Completion, Prompt};
 use chrono::Local;
+use rocket::{FromForm, FromFormField};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, FromFormField, Clone, PartialEq, Eq, Hash, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum Status {
+    Scheduled,
     Pending,
     Running,
     Finished,
     Failed,
+    Cancelled,
+}
+
+impl Status {
+    pub fn is_completed(&self) -> bool {
+        matches!(self, Status::Finished | Status::Failed | Status::Cancelled)
+    }
+
+    pub fn all() -> [Status; 6] {
+        [
+            Status::Scheduled,
+            Status::Pending,
+            Status::Running,
+            Status::Finished,
+            Status::Failed,
+            Status::Cancelled,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Scheduled => "scheduled",
+            Status::Pending => "pending",
+            Status::Running => "running",
+            Status::Finished => "finished",
+            Status::Failed => "failed",
+            Status::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    pub fn all() -> [Priority; 3] {
+        [Priority::High, Priority::Normal, Priority::Low]
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct QueueDepth {
+    pub priority: Priority,
+    pub depth: usize,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -20,31 +76,211 @@ pub struct Task {
     pub prompt: Prompt,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub options: GenerationOptions,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
     pub completion: Option<Completion>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub err_msg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub callback_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub batch_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub cron_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub submitter: Option<String>,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub requeues: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub history: Vec<Attempt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub run_at: Option<DateTime<Local>>,
     pub create_time: DateTime<Local>,
     pub finish_time: Option<DateTime<Local>>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Attempt {
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub error: Option<String>,
+    pub finish_time: Option<DateTime<Local>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeadLetter {
+    pub task_id: String,
+    pub reason: String,
+    pub dead_time: DateTime<Local>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_deserializing)]
+    pub task: Option<TaskSummary>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Batch {
+    pub id: String,
+    pub task_ids: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BatchStatus {
+    pub id: String,
+    pub total: usize,
+    pub counts: HashMap<Status, usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tasks: Option<Vec<Task>>,
+}
+
+#[derive(FromForm, Default, Debug)]
+pub struct TaskFilter {
+    pub status: Option<Status>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub model: Option<String>,
+    pub submitter: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TaskSummary {
+    pub id: String,
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub priority: Priority,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cron_id: Option<String>,
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub err_msg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_at: Option<DateTime<Local>>,
+    pub create_time: DateTime<Local>,
+    pub finish_time: Option<DateTime<Local>>,
+}
+
+impl From<Task> for TaskSummary {
+    fn from(task: Task) -> Self {
+        Self {
+            id: task.id,
+            status: task.status,
+            model: task.model,
+            priority: task.priority,
+            submitter: task.submitter,
+            batch_id: task.batch_id,
+            cron_id: task.cron_id,
+            attempts: task.attempts,
+            err_msg: task.err_msg,
+            run_at: task.run_at,
+            create_time: task.create_time,
+            finish_time: task.finish_time,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct TaskPage {
+    pub tasks: Vec<TaskSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub id: String,
+    pub event: String,
+    pub data: String,
+}
+
+impl Progress {
+    pub fn error(id: &str, err: anyhow::Error) -> Self {
+        Self {
+            id: id.to_owned(),
+            event: "error".to_owned(),
+            data: serde_json::json!({ "msg": format!("{:#}", err) }).to_string(),
+        }
+    }
+}
+
 impl Task {
-    pub fn create(message: Message) -> Self {
+    pub fn create(request: ChatRequest) -> Self {
+        let ChatRequest {
+            message,
+            model,
+            options,
+        } = request;
         Self {
             id: Uuid::new_v4().to_string(),
             status: Status::Pending,
             prompt: message.into(),
+            model,
+            options: options.unwrap_or_default(),
+            priority: Priority::default(),
             completion: None,
             err_msg: None,
+            callback_url: None,
+            batch_id: None,
+            cron_id: None,
+            submitter: None,
+            attempts: 0,
+            requeues: 0,
+            last_error: None,
+            history: Vec::new(),
+            run_at: None,
             create_time: DateTime::local(),
             finish_time: None,
         }
     }
+
+    pub fn schedule(&mut self, run_at: DateTime<Local>) {
+        if *run_at > Local::now() {
+            self.status = Status::Scheduled;
+        }
+        self.run_at = Some(run_at);
+    }
+
+    pub fn retry(&mut self) {
+        self.history.push(Attempt {
+            attempts: self.attempts,
+            error: self.err_msg.take().or(self.last_error.take()),
+            finish_time: self.finish_time.take(),
+        });
+        self.status = Status::Pending;
+        self.attempts = 0;
+        self.requeues = 0;
+        self.last_error = None;
+        self.completion = None;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::entities::message::Message;
 
     #[test]
     fn test_ser_task() {
@@ -55,8 +291,40 @@ mod tests {
             videos: None,
             context: None,
         };
-        let task = Task::create(message);
+        let task = Task::create(ChatRequest {
+            message,
+            model: None,
+            options: None,
+        });
         let json = serde_json::to_string(&task).unwrap();
         println!("{}", json);
     }
+
+    #[test]
+    fn test_retry_task() {
+        let message = Message {
+            role: None,
+            text: Some("你是谁".to_owned()),
+            images: None,
+            videos: None,
+            context: None,
+        };
+        let mut task = Task::create(ChatRequest {
+            message,
+            model: None,
+            options: None,
+        });
+        task.status = Status::Failed;
+        task.attempts = 3;
+        task.requeues = 1;
+        task.err_msg = Some("timeout".to_owned());
+        task.finish_time = Some(DateTime::local());
+        task.retry();
+        assert_eq!(task.status, Status::Pending);
+        assert_eq!((task.attempts, task.requeues), (0, 0));
+        assert!(task.err_msg.is_none() && task.finish_time.is_none());
+        assert_eq!(task.history.len(), 1);
+        assert_eq!(task.history[0].attempts, 3);
+        assert_eq!(task.history[0].error.as_deref(), Some("timeout"));
+    }
 }
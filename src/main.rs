@@ -5,19 +5,83 @@ mod services;
 
 use crate::databases::Tasks;
 use crate::entities::config::Config;
-use crate::routes::{chat, file, task};
+use crate::routes::{admin, chat, cron, file, openai, task};
+use crate::services::cron::CronScheduler;
+use crate::services::executor::Executor;
+use crate::services::models::ModelRegistry;
+use crate::services::webhook::Webhook;
 use rocket::fairing::AdHoc;
-use rocket::{launch, routes};
+use rocket::{catchers, launch, routes};
 use rocket_db_pools::Database;
+use std::env;
 
 #[launch]
 fn rocket() -> _ {
-    rocket::build()
+    let rocket = rocket::build()
         .attach(Tasks::init())
+        .attach(Tasks::register())
         .attach(AdHoc::config::<Config>())
-        .mount("/chat", routes![chat::completion, chat::stream])
-        .mount("/task", routes![task::create, task::query, task::result])
-        .mount("/file", routes![file::upload, file::download])
+        .attach(ModelRegistry::register())
+        .attach(Executor::workers())
+        .attach(Executor::shutdown())
+        .attach(Executor::scheduler())
+        .attach(CronScheduler::register())
+        .attach(Webhook::resume());
+    if env::args().any(|arg| arg == "--worker") {
+        return rocket;
+    }
+    rocket
+        .mount(
+            "/chat",
+            routes![chat::completion, chat::stream, chat::events],
+        )
+        .mount(
+            "/task",
+            routes![
+                task::create,
+                task::create_batch,
+                task::batch,
+                task::batch_results,
+                task::list,
+                task::query,
+                task::result,
+                task::cancel,
+                task::stream,
+                task::queues
+            ],
+        )
+        .mount(
+            "/cron",
+            routes![
+                cron::create,
+                cron::query,
+                cron::list,
+                cron::update,
+                cron::delete
+            ],
+        )
+        .mount(
+            "/admin",
+            routes![
+                admin::dead_letters,
+                admin::requeue,
+                admin::requeue_all,
+                admin::purge,
+                admin::uploads,
+                admin::purge_uploads
+            ],
+        )
+        .mount(
+            "/file",
+            routes![
+                file::upload,
+                file::download,
+                file::presign_upload,
+                file::presign_download
+            ],
+        )
+        .mount("/v1", routes![openai::completions, openai::models])
+        .register("/v1", catchers![openai::catcher])
 }
 
 #[cfg(test)]
@@ -25,7 +89,7 @@ mod tests {
     use crate::entities::message::Message;
     use crate::entities::task::{Status, Task};
     use crate::rocket;
-    use crate::routes::{chat, task};
+    use crate::routes::{chat, file, task};
     use agentx::{Completion, Role};
     use rocket::http::Status as HttpStatus;
     use rocket::local::blocking::Client;
@@ -53,6 +117,21 @@ mod tests {
         println!("{:?}", completion);
     }
 
+    #[test]
+    fn test_chat_unknown_model() {
+        let client = Client::tracked(rocket()).unwrap();
+        let response = client
+            .post(uri!("/chat", chat::completion))
+            .json(&serde_json::json!({
+                "text": "你是谁",
+                "model": "not-existed",
+            }))
+            .dispatch();
+        assert_eq!(response.status(), HttpStatus::BadRequest);
+        let json: Value = response.into_json().unwrap();
+        assert!(!json["success"].as_bool().unwrap());
+    }
+
     #[test]
     fn test_task() {
         let client = Client::tracked(rocket()).unwrap();
@@ -81,4 +160,32 @@ mod tests {
         println!("{:?}", task);
         assert_eq!(task.status, Status::Finished);
     }
+
+    #[test]
+    fn test_cancel_missing_task() {
+        let client = Client::tracked(rocket()).unwrap();
+        let id = uuid::Uuid::new_v4().to_string();
+        let response = client.post(uri!("/task", task::cancel(id = id))).dispatch();
+        assert_eq!(response.status(), HttpStatus::NotFound);
+    }
+
+    #[test]
+    fn test_result_missing_task() {
+        let client = Client::tracked(rocket()).unwrap();
+        let id = uuid::Uuid::new_v4().to_string();
+        let response = client
+            .get(uri!("/task", task::result(id = id, timeout = _)))
+            .dispatch();
+        assert_eq!(response.status(), HttpStatus::NotFound);
+    }
+
+    #[test]
+    fn test_download_missing_file() {
+        let client = Client::tracked(rocket()).unwrap();
+        let name = format!("{}.txt", uuid::Uuid::new_v4());
+        let response = client
+            .get(uri!("/file", file::download(name.as_str())))
+            .dispatch();
+        assert_eq!(response.status(), HttpStatus::NotFound);
+    }
 }
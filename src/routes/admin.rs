@@ -0,0 +1,68 @@
+use crate::databases::Tasks;
+use crate::entities::admin::Admin;
+use crate::entities::oss::MultipartUpload;
+use crate::entities::response::Response;
+use crate::entities::task::{DeadLetter, Task};
+use crate::services::executor::Executor;
+use crate::services::oss::Storage;
+use crate::services::Service;
+use rocket::{get, post};
+use rocket_db_pools::Connection;
+
+#[get("/dead_letter/list?<offset>&<limit>")]
+pub async fn dead_letters(
+    _admin: Admin,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    executor: &Service<Executor>,
+    mut conn: Connection<Tasks>,
+) -> Response<Vec<DeadLetter>> {
+    Response::invoke(async {
+        executor
+            .dead_letters(&mut conn, offset.unwrap_or(0), limit.unwrap_or(20))
+            .await
+    })
+    .await
+}
+
+#[post("/dead_letter/requeue?<id>")]
+pub async fn requeue(
+    _admin: Admin,
+    id: String,
+    executor: &Service<Executor>,
+    mut conn: Connection<Tasks>,
+) -> Response<Task> {
+    Response::invoke(async { executor.requeue_dead_letter(&mut conn, &id).await }).await
+}
+
+#[post("/dead_letter/requeue_all")]
+pub async fn requeue_all(
+    _admin: Admin,
+    executor: &Service<Executor>,
+    mut conn: Connection<Tasks>,
+) -> Response<usize> {
+    Response::invoke(async { executor.requeue_dead_letters(&mut conn).await }).await
+}
+
+#[post("/dead_letter/purge")]
+pub async fn purge(
+    _admin: Admin,
+    executor: &Service<Executor>,
+    mut conn: Connection<Tasks>,
+) -> Response<usize> {
+    Response::invoke(async { executor.purge_dead_letters(&mut conn).await }).await
+}
+
+#[get("/upload/list")]
+pub async fn uploads(_admin: Admin, storage: &Service<Storage>) -> Response<Vec<MultipartUpload>> {
+    Response::invoke(async { storage.list_uploads().await }).await
+}
+
+#[post("/upload/purge?<max_age>")]
+pub async fn purge_uploads(
+    _admin: Admin,
+    max_age: Option<u64>,
+    storage: &Service<Storage>,
+) -> Response<Vec<MultipartUpload>> {
+    Response::invoke(async { storage.abort_stale_uploads(max_age).await }).await
+}
@@ -1,49 +1,104 @@
-use crate::entities::message::Message;
-use crate::entities::response::Response;
-use crate::services::models::{Qwen3, Qwen3VL};
+use crate::entities::request::ChatRequest;
+use crate::entities::response::{BadRequest, Response};
+use crate::services::models::ModelRegistry;
 use crate::services::Service;
-use agentx::Completion;
+use agentx::{Completion, Prompt};
+use futures::StreamExt;
 use rocket::http::Status;
 use rocket::post;
 use rocket::response::status;
-use rocket::response::stream::TextStream;
+use rocket::response::stream::{Event, EventStream, TextStream};
 use rocket::serde::json::Json;
+use serde_json::json;
 
-#[post("/completion", data = "<message>")]
+#[post("/completion", data = "<request>")]
 pub async fn completion(
-    message: Json<Message>,
-    qwen3: &Service<Qwen3>,
-    qwen3vl: &Service<Qwen3VL>,
-) -> Json<Response<Completion>> {
+    request: Json<ChatRequest>,
+    registry: &Service<ModelRegistry>,
+) -> Response<Completion> {
     Response::invoke(async {
-        let message = message.into_inner();
-        let completion = if message.only_text() {
-            qwen3.completion(&message.into()).await?
-        } else {
-            qwen3vl.completion(&message.into()).await?
-        };
-        Ok(completion)
+        let ChatRequest {
+            message,
+            model,
+            options,
+        } = request.into_inner();
+        let prompt: Prompt = message.into();
+        let model = registry.select(model.as_deref(), &prompt)?;
+        model.completion(&prompt, &model.resolve(options)).await
     })
     .await
-    .into()
 }
 
-#[post("/stream", data = "<message>")]
+#[post("/stream", data = "<request>")]
 pub async fn stream(
-    message: Json<Message>,
-    qwen3: &Service<Qwen3>,
-    qwen3vl: &Service<Qwen3VL>,
+    request: Json<ChatRequest>,
+    registry: &Service<ModelRegistry>,
 ) -> Result<TextStream![String], status::Custom<String>> {
-    let message = message.into_inner();
-    let result = if message.only_text() {
-        qwen3.text_stream(&message.into()).await
-    } else {
-        qwen3vl.text_stream(&message.into()).await
+    let ChatRequest {
+        message,
+        model,
+        options,
+    } = request.into_inner();
+    let prompt: Prompt = message.into();
+    let result = match registry.select(model.as_deref(), &prompt) {
+        Ok(model) => model.text_stream(&prompt, &model.resolve(options)).await,
+        Err(err) => Err(err),
     };
     result
         .map(|stream| TextStream::from(stream.into_inner()))
         .map_err(|err| {
-            eprint!("Failed to streaming chat: {:?}", err);
-            status::Custom(Status::InternalServerError, format!("{:#}", err))
+            eprintln!("Failed to streaming chat: {:?}", err);
+            let status = if err.downcast_ref::<BadRequest>().is_some() {
+                Status::BadRequest
+            } else {
+                Status::InternalServerError
+            };
+            status::Custom(status, format!("{:#}", err))
         })
 }
+
+#[post("/events", data = "<request>")]
+pub async fn events(
+    request: Json<ChatRequest>,
+    registry: &Service<ModelRegistry>,
+) -> Result<EventStream![], status::Custom<String>> {
+    let ChatRequest {
+        message,
+        model,
+        options,
+    } = request.into_inner();
+    let prompt: Prompt = message.into();
+    let model = registry
+        .select(model.as_deref(), &prompt)
+        .map_err(|err| status::Custom(Status::BadRequest, format!("{:#}", err)))?;
+    let result = model.stream(&prompt, &model.resolve(options)).await;
+    Ok(EventStream! {
+        match result {
+            Ok(mut stream) => {
+                while let Some(Completion {
+                    reasoning_content,
+                    content,
+                    usage,
+                }) = stream.next().await
+                {
+                    if let Some(reasoning_content) = reasoning_content {
+                        yield Event::json(&json!({ "reasoning_content": reasoning_content }))
+                            .event("reasoning");
+                    }
+                    if let Some(content) = content {
+                        yield Event::json(&json!({ "content": content })).event("content");
+                    }
+                    if let Some(usage) = usage {
+                        yield Event::json(&usage).event("usage");
+                    }
+                }
+                // Not every upstream reports usage, so the end of the stream is the end of the chat.
+                yield Event::json(&json!({})).event("done");
+            }
+            Err(err) => {
+                eprintln!("Failed to streaming chat: {:?}", err);
+                yield Event::json(&json!({ "msg": format!("{:#}", err) })).event("error");
+            }
+        }
+    })
+}
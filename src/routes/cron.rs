@@ -0,0 +1,60 @@
+use crate::databases::Tasks;
+use crate::entities::cron::{Cron, CronRequest};
+use crate::entities::response::Response;
+use crate::services::cron::CronScheduler;
+use crate::services::Service;
+use anyhow::anyhow;
+use rocket::serde::json::Json;
+use rocket::{get, post};
+use rocket_db_pools::Connection;
+
+#[post("/create", data = "<request>")]
+pub async fn create(
+    request: Json<CronRequest>,
+    scheduler: &Service<CronScheduler>,
+    mut conn: Connection<Tasks>,
+) -> Response<Cron> {
+    Response::invoke(async { scheduler.create(&mut conn, request.into_inner()).await }).await
+}
+
+#[get("/query?<id>")]
+pub async fn query(
+    id: String,
+    scheduler: &Service<CronScheduler>,
+    mut conn: Connection<Tasks>,
+) -> Response<Cron> {
+    Response::invoke(async {
+        scheduler
+            .get(&mut conn, &id)
+            .await?
+            .ok_or_else(|| anyhow!("Cron '{id}' not existed"))
+    })
+    .await
+}
+
+#[get("/list")]
+pub async fn list(
+    scheduler: &Service<CronScheduler>,
+    mut conn: Connection<Tasks>,
+) -> Response<Vec<Cron>> {
+    Response::invoke(async { scheduler.list(&mut conn).await }).await
+}
+
+#[post("/update?<id>", data = "<request>")]
+pub async fn update(
+    id: String,
+    request: Json<CronRequest>,
+    scheduler: &Service<CronScheduler>,
+    mut conn: Connection<Tasks>,
+) -> Response<Cron> {
+    Response::invoke(async { scheduler.update(&mut conn, &id, request.into_inner()).await }).await
+}
+
+#[post("/delete?<id>")]
+pub async fn delete(
+    id: String,
+    scheduler: &Service<CronScheduler>,
+    mut conn: Connection<Tasks>,
+) -> Response<Cron> {
+    Response::invoke(async { scheduler.delete(&mut conn, &id).await }).await
+}
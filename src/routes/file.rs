@@ -1,44 +1,116 @@
-use crate::entities::oss::ObjectMeta;
-use crate::entities::response::Response;
-use crate::services::oss::OSS;
+use crate::entities::oss::{format_http_date, ByteRange, ObjectMeta, Preconditions, PresignedUrl};
+use crate::entities::response::{BadRequest, NotFound, Response};
+use crate::services::oss::{self, Storage};
 use crate::services::Service;
 use bytes::Bytes;
-use futures::Stream;
-use rocket::http::{Header, Status};
-use rocket::response::stream::ByteStream;
+use futures::{future, stream, Stream, StreamExt};
+use reqwest::Method;
+use rocket::data::ToByteUnit;
+use rocket::http::{ContentType, Header, Status};
 use rocket::response::{status, Responder};
 use rocket::serde::json::Json;
 use rocket::{get, post, Data, Request};
+use std::io;
+use std::str::FromStr;
+use tokio_util::io::StreamReader;
+use uuid::Uuid;
+
+static UPLOAD_MAX_SIZE: usize = 512 * 1024 * 1024; // 512MB
 
 #[post("/upload", data = "<data>")]
 pub async fn upload(
     data: Data<'_>,
     meta: ObjectMeta,
-    oss: &Service<OSS>,
+    storage: &Service<Storage>,
 ) -> Json<Response<String>> {
-    Response::invoke(async { oss.put_object(data, meta).await })
-        .await
-        .into()
+    Response::invoke(async {
+        let name = format!("{}.{}", Uuid::new_v4(), meta.extension()?);
+        let reader = data.open(UPLOAD_MAX_SIZE.bytes());
+        storage.put(&name, Box::pin(reader), meta).await?;
+        Ok(name)
+    })
+    .await
+    .into()
 }
 
-pub enum FileResponder<S: Stream<Item = Bytes> + Send> {
+pub enum FileResponder<S: Stream<Item = io::Result<Bytes>> + Send> {
     Ok(S, ObjectMeta),
+    Partial(S, ObjectMeta, (u64, u64)),
+    Multipart(S, ObjectMeta, String, u64),
+    NotModified(ObjectMeta),
+    Unsatisfiable(ObjectMeta),
     Err(Status, anyhow::Error),
 }
 
-impl<'r, S: Stream<Item = Bytes> + Send + 'r> Responder<'r, 'r> for FileResponder<S> {
+fn validators(builder: &mut rocket::response::Builder<'_>, meta: &ObjectMeta) {
+    builder.header(Header::new("Accept-Ranges", "bytes"));
+    if let Some(e_tag) = &meta.e_tag {
+        builder.header(Header::new("ETag", e_tag.clone()));
+    }
+    if let Some(last_modified) = &meta.last_modified {
+        builder.header(Header::new(
+            "Last-Modified",
+            format_http_date(last_modified),
+        ));
+    }
+}
+
+impl<'r, S: Stream<Item = io::Result<Bytes>> + Send + 'r> Responder<'r, 'r> for FileResponder<S> {
     fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'r> {
         match self {
             Self::Ok(stream, meta) => {
-                let mut builder =
-                    rocket::Response::build_from(ByteStream::from(stream).respond_to(request)?);
-                if let Ok(content_type) = meta.content_type() {
-                    builder.header(content_type);
-                }
+                // Storage errors surface as a failed body instead of a silently short one.
+                let mut builder = rocket::Response::build();
+                builder.streamed_body(StreamReader::new(stream));
+                builder.header(meta.content_type().unwrap_or(ContentType::Binary));
                 builder.header(Header::new(
                     "Content-Length",
                     meta.content_length.to_string(),
                 ));
+                validators(&mut builder, &meta);
+                builder.ok()
+            }
+            Self::Partial(stream, meta, (start, end)) => {
+                let mut builder = rocket::Response::build();
+                builder.streamed_body(StreamReader::new(stream));
+                builder.status(Status::PartialContent);
+                builder.header(meta.content_type().unwrap_or(ContentType::Binary));
+                builder.header(Header::new("Content-Length", (end - start + 1).to_string()));
+                builder.header(Header::new(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", start, end, meta.content_length),
+                ));
+                validators(&mut builder, &meta);
+                builder.ok()
+            }
+            Self::Multipart(stream, meta, boundary, content_length) => {
+                let mut builder = rocket::Response::build();
+                builder.streamed_body(StreamReader::new(stream));
+                builder.status(Status::PartialContent);
+                builder.header(Header::new(
+                    "Content-Type",
+                    format!("multipart/byteranges; boundary={}", boundary),
+                ));
+                builder.header(Header::new("Content-Length", content_length.to_string()));
+                validators(&mut builder, &meta);
+                builder.ok()
+            }
+            Self::NotModified(meta) => {
+                let mut builder = rocket::Response::build();
+                builder.status(Status::NotModified);
+                validators(&mut builder, &meta);
+                builder.ok()
+            }
+            Self::Unsatisfiable(meta) => {
+                let mut builder = rocket::Response::build_from(
+                    status::Custom(Status::RangeNotSatisfiable, "Range Not Satisfiable")
+                        .respond_to(request)?,
+                );
+                builder.header(Header::new(
+                    "Content-Range",
+                    format!("bytes */{}", meta.content_length),
+                ));
+                validators(&mut builder, &meta);
                 builder.ok()
             }
             Self::Err(status, err) => rocket::Response::build_from(
@@ -52,13 +124,104 @@ impl<'r, S: Stream<Item = Bytes> + Send + 'r> Responder<'r, 'r> for FileResponde
 #[get("/download/<name>")]
 pub async fn download(
     name: &str,
-    oss: &Service<OSS>,
-) -> FileResponder<impl Stream<Item = Bytes> + Send> {
-    match oss.get_object(name).await {
-        Ok((stream, meta)) => FileResponder::Ok(stream, meta),
+    preconditions: Preconditions,
+    storage: &Service<Storage>,
+) -> FileResponder<oss::Stream<io::Result<Bytes>>> {
+    let result: anyhow::Result<FileResponder<_>> = async {
+        storage.check_public(name)?;
+        let meta = storage.head(name).await?;
+        if preconditions.is_not_modified(&meta) {
+            return Ok(FileResponder::NotModified(meta));
+        }
+        match preconditions.byte_range(&meta) {
+            ByteRange::Full if meta.content_length == 0 => {
+                let stream: oss::Stream<io::Result<Bytes>> = Box::pin(stream::empty());
+                Ok(FileResponder::Ok(stream, meta))
+            }
+            ByteRange::Full => {
+                let stream = storage
+                    .get_range(name, (0, meta.content_length - 1))
+                    .await?;
+                Ok(FileResponder::Ok(stream, meta))
+            }
+            ByteRange::Unsatisfiable => Ok(FileResponder::Unsatisfiable(meta)),
+            ByteRange::Partial(ranges) if ranges.len() == 1 => {
+                let range = ranges[0];
+                let stream = storage.get_range(name, range).await?;
+                Ok(FileResponder::Partial(stream, meta, range))
+            }
+            ByteRange::Partial(ranges) => {
+                let boundary = Uuid::new_v4().simple().to_string();
+                let mut parts = Vec::new();
+                let mut content_length = 0;
+                for (start, end) in ranges {
+                    let header = format!(
+                        "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                        boundary, meta.content_type, start, end, meta.content_length
+                    );
+                    content_length += header.len() as u64 + end - start + 1;
+                    let part = storage.get_range(name, (start, end)).await?;
+                    parts.push(
+                        stream::once(future::ready(Ok(Bytes::from(header))))
+                            .chain(part)
+                            .boxed(),
+                    );
+                }
+                let trailer = format!("\r\n--{}--\r\n", boundary);
+                content_length += trailer.len() as u64;
+                parts.push(stream::once(future::ready(Ok(Bytes::from(trailer)))).boxed());
+                let stream: oss::Stream<io::Result<Bytes>> =
+                    Box::pin(stream::iter(parts).flatten());
+                Ok(FileResponder::Multipart(
+                    stream,
+                    meta,
+                    boundary,
+                    content_length,
+                ))
+            }
+        }
+    }
+    .await;
+    match result {
+        Ok(responder) => responder,
         Err(err) => {
-            eprint!("Failed to download file '{}': {:?}", name, err);
-            FileResponder::Err(Status::InternalServerError, err)
+            eprintln!("Failed to download file '{}': {:?}", name, err);
+            let status = if err.downcast_ref::<NotFound>().is_some() {
+                Status::NotFound
+            } else {
+                Status::InternalServerError
+            };
+            FileResponder::Err(status, err)
         }
     }
 }
+
+#[post("/presign/upload?<content_type>&<expires>")]
+pub async fn presign_upload(
+    content_type: &str,
+    expires: Option<u64>,
+    storage: &Service<Storage>,
+) -> Response<PresignedUrl> {
+    Response::invoke(async {
+        let extension = ContentType::from_str(content_type)
+            .ok()
+            .and_then(|content_type| content_type.extension().map(ToString::to_string))
+            .ok_or_else(|| BadRequest(format!("Invalid content type '{}'", content_type)))?;
+        let name = format!("{}.{}", Uuid::new_v4(), extension);
+        storage.presign(Method::PUT, &name, expires)
+    })
+    .await
+}
+
+#[get("/presign/download/<name>?<expires>")]
+pub async fn presign_download(
+    name: &str,
+    expires: Option<u64>,
+    storage: &Service<Storage>,
+) -> Response<PresignedUrl> {
+    Response::invoke(async {
+        storage.check_public(name)?;
+        storage.presign(Method::GET, name, expires)
+    })
+    .await
+}
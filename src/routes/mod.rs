@@ -1,3 +1,6 @@
+pub mod admin;
 pub mod chat;
+pub mod cron;
 pub mod file;
+pub mod openai;
 pub mod task;
@@ -0,0 +1,182 @@
+use crate::entities::datetime::DateTime;
+use crate::entities::openai::{
+    ChatCompletion, ChatCompletionRequest, Choice, ChoiceMessage, ErrorObject, ErrorResponse,
+    ModelList, ModelObject, Usage,
+};
+use crate::services::models::ModelRegistry;
+use crate::services::Service;
+use agentx::Completion;
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use rocket::http::Status;
+use rocket::response::stream::{Event, EventStream};
+use rocket::response::{status, Responder};
+use rocket::serde::json::Json;
+use rocket::{catch, get, post, Request};
+use uuid::Uuid;
+
+pub enum ChatCompletionResponder<S: Stream<Item = Event> + Send> {
+    Completion(ChatCompletion),
+    Stream(S),
+    Err(Status, anyhow::Error),
+}
+
+fn error(status: Status, message: String) -> status::Custom<Json<ErrorResponse>> {
+    let r#type = if status.code < 500 {
+        "invalid_request_error"
+    } else {
+        "server_error"
+    };
+    status::Custom(
+        status,
+        Json(ErrorResponse {
+            error: ErrorObject { message, r#type },
+        }),
+    )
+}
+
+impl<'r, S: Stream<Item = Event> + Send + 'r> Responder<'r, 'r> for ChatCompletionResponder<S> {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'r> {
+        match self {
+            Self::Completion(completion) => Json(completion).respond_to(request),
+            Self::Stream(stream) => EventStream::from(stream).respond_to(request),
+            Self::Err(status, err) => error(status, format!("{:#}", err)).respond_to(request),
+        }
+    }
+}
+
+#[catch(default)]
+pub fn catcher(status: Status, _: &Request) -> status::Custom<Json<ErrorResponse>> {
+    error(status, status.reason_lossy().to_owned())
+}
+
+fn chunk(
+    id: &str,
+    created: i64,
+    model: &str,
+    delta: ChoiceMessage,
+    finish_reason: Option<&'static str>,
+) -> ChatCompletion {
+    ChatCompletion {
+        id: id.to_owned(),
+        object: "chat.completion.chunk",
+        created,
+        model: model.to_owned(),
+        choices: vec![Choice {
+            index: 0,
+            message: None,
+            delta: Some(delta),
+            finish_reason,
+        }],
+        usage: None,
+    }
+}
+
+#[post("/chat/completions", data = "<request>")]
+pub async fn completions(
+    request: Json<ChatCompletionRequest>,
+    registry: &Service<ModelRegistry>,
+) -> ChatCompletionResponder<impl Stream<Item = Event> + Send> {
+    let request = request.into_inner();
+    let prompt = request.prompt();
+    let model = match registry.select(request.model.as_deref(), &prompt) {
+        Ok(model) => model,
+        Err(err) => return ChatCompletionResponder::Err(Status::BadRequest, err),
+    };
+    let options = model.resolve(Some(request.options()));
+    let id = format!("chatcmpl-{}", Uuid::new_v4().simple());
+    let created = DateTime::utc().timestamp();
+    let name = model.name().to_owned();
+    if !request.stream {
+        return match model.completion(&prompt, &options).await {
+            Ok(Completion {
+                reasoning_content,
+                content,
+                usage,
+            }) => {
+                let usage = usage
+                    .and_then(|usage| serde_json::to_value(usage).ok())
+                    .and_then(|usage| Usage::from_value(&usage));
+                let finish_reason = Usage::finish_reason(usage.as_ref(), options.max_tokens);
+                ChatCompletionResponder::Completion(ChatCompletion {
+                    id,
+                    object: "chat.completion",
+                    created,
+                    model: name,
+                    choices: vec![Choice {
+                        index: 0,
+                        message: Some(ChoiceMessage {
+                            role: Some("assistant"),
+                            content,
+                            reasoning_content,
+                        }),
+                        delta: None,
+                        finish_reason: Some(finish_reason),
+                    }],
+                    usage,
+                })
+            }
+            Err(err) => {
+                eprintln!("Failed to chat completion: {:?}", err);
+                ChatCompletionResponder::Err(Status::InternalServerError, err)
+            }
+        };
+    }
+    match model.stream(&prompt, &options).await {
+        Ok(mut completions) => ChatCompletionResponder::Stream(stream! {
+            let delta = ChoiceMessage {
+                role: Some("assistant"),
+                content: Some(String::new()),
+                reasoning_content: None,
+            };
+            yield Event::json(&chunk(&id, created, &name, delta, None));
+            let mut usage_mapped = None;
+            while let Some(Completion {
+                reasoning_content,
+                content,
+                usage,
+            }) = completions.next().await
+            {
+                if reasoning_content.is_some() || content.is_some() {
+                    let delta = ChoiceMessage {
+                        role: None,
+                        content,
+                        reasoning_content,
+                    };
+                    yield Event::json(&chunk(&id, created, &name, delta, None));
+                }
+                if let Some(usage) = usage {
+                    usage_mapped = serde_json::to_value(usage)
+                        .ok()
+                        .and_then(|usage| Usage::from_value(&usage));
+                }
+            }
+            let finish_reason = Usage::finish_reason(usage_mapped.as_ref(), options.max_tokens);
+            let mut last = chunk(&id, created, &name, ChoiceMessage::default(), Some(finish_reason));
+            last.usage = usage_mapped;
+            yield Event::json(&last);
+            yield Event::data("[DONE]");
+        }),
+        Err(err) => {
+            eprintln!("Failed to streaming chat completion: {:?}", err);
+            ChatCompletionResponder::Err(Status::InternalServerError, err)
+        }
+    }
+}
+
+#[get("/models")]
+pub async fn models(registry: &Service<ModelRegistry>) -> Json<ModelList> {
+    Json(ModelList {
+        object: "list",
+        data: registry
+            .names()
+            .into_iter()
+            .map(|name| ModelObject {
+                id: name.to_owned(),
+                object: "model",
+                created: 0,
+                owned_by: "rocket-agentx",
+            })
+            .collect(),
+    })
+}
@@ -1,26 +1,170 @@
 use crate::databases::Tasks;
-use crate::entities::message::Message;
-use crate::entities::response::Response;
-use crate::entities::task::Task;
+use crate::entities::datetime::DateTime;
+use crate::entities::event::LastEventId;
+use crate::entities::request::TaskRequest;
+use crate::entities::response::{BadRequest, Response};
+use crate::entities::task::{Batch, BatchStatus, QueueDepth, Task, TaskFilter, TaskPage};
 use crate::services::executor::Executor;
+use crate::services::models::ModelRegistry;
+use crate::services::webhook::Webhook;
 use crate::services::Service;
+use chrono::{Local, TimeDelta};
+use futures::StreamExt;
+use rocket::http::{ContentType, Status};
+use rocket::response::status;
+use rocket::response::stream::{ByteStream, Event, EventStream};
 use rocket::serde::json::Json;
 use rocket::{get, post};
 use rocket_db_pools::Connection;
+use uuid::Uuid;
 
-#[post("/create", data = "<message>")]
+static MAX_BATCH_SIZE: usize = 10000;
+
+fn build(request: TaskRequest, registry: &ModelRegistry) -> anyhow::Result<(Task, Option<String>)> {
+    let TaskRequest {
+        chat,
+        callback_url,
+        callback_secret,
+        priority,
+        submitter,
+        run_at,
+        delay_secs,
+    } = request;
+    let mut task = Task::create(chat);
+    task.priority = priority.unwrap_or_default();
+    let model = registry.select(task.model.as_deref(), &task.prompt)?;
+    task.model = Some(model.name().to_owned());
+    task.options = model.resolve(Some(task.options));
+    task.callback_url = callback_url;
+    task.submitter = submitter;
+    let run_at = match (run_at, delay_secs) {
+        (Some(_), Some(_)) => {
+            return Err(
+                BadRequest("Only one of 'run_at' and 'delay_secs' is allowed".to_owned()).into(),
+            );
+        }
+        (Some(run_at), None) => Some(run_at),
+        (None, Some(delay_secs)) => {
+            let run_at = i64::try_from(delay_secs)
+                .ok()
+                .and_then(TimeDelta::try_seconds)
+                .and_then(|delay| Local::now().checked_add_signed(delay))
+                .ok_or_else(|| BadRequest(format!("Invalid 'delay_secs' {delay_secs}")))?;
+            Some(DateTime::from(run_at))
+        }
+        (None, None) => None,
+    };
+    if let Some(run_at) = run_at {
+        task.schedule(run_at);
+    }
+    Ok((task, callback_secret))
+}
+
+#[post("/create", data = "<request>")]
 pub async fn create(
-    message: Json<Message>,
+    request: Json<TaskRequest>,
+    registry: &Service<ModelRegistry>,
     executor: &Service<Executor>,
-    conn: Connection<Tasks>,
-) -> Json<Response<Task>> {
+    webhook: &Service<Webhook>,
+    mut conn: Connection<Tasks>,
+) -> Response<Task> {
     Response::invoke(async {
-        let task = Task::create(message.into_inner());
-        executor.submit(conn, &task).await?;
+        let (task, callback_secret) = build(request.into_inner(), registry)?;
+        if let Some(callback_url) = &task.callback_url {
+            webhook
+                .register(&mut conn, &task, callback_url, callback_secret.as_deref())
+                .await?;
+        }
+        executor.submit(&mut conn, &task).await?;
         Ok(task)
     })
     .await
-    .into()
+}
+
+#[post("/batch", data = "<requests>")]
+pub async fn create_batch(
+    requests: Json<Vec<TaskRequest>>,
+    registry: &Service<ModelRegistry>,
+    executor: &Service<Executor>,
+    webhook: &Service<Webhook>,
+    mut conn: Connection<Tasks>,
+) -> Response<Batch> {
+    Response::invoke(async {
+        let requests = requests.into_inner();
+        if requests.is_empty() || requests.len() > MAX_BATCH_SIZE {
+            return Err(BadRequest(format!(
+                "Batch size must be between 1 and {}",
+                MAX_BATCH_SIZE
+            ))
+            .into());
+        }
+        let batch_id = Uuid::new_v4().to_string();
+        let mut tasks = Vec::with_capacity(requests.len());
+        for request in requests {
+            let (mut task, callback_secret) = build(request, registry)?;
+            task.batch_id = Some(batch_id.clone());
+            tasks.push((task, callback_secret));
+        }
+        for (task, callback_secret) in &tasks {
+            if let Some(callback_url) = &task.callback_url {
+                webhook
+                    .register(&mut conn, task, callback_url, callback_secret.as_deref())
+                    .await?;
+            }
+        }
+        let tasks = tasks.into_iter().map(|(task, _)| task).collect::<Vec<_>>();
+        executor.submit_batch(&mut conn, &batch_id, &tasks).await?;
+        Ok(Batch {
+            id: batch_id,
+            task_ids: tasks.into_iter().map(|task| task.id).collect(),
+        })
+    })
+    .await
+}
+
+#[get("/batch?<id>&<tasks>")]
+pub async fn batch(
+    id: String,
+    tasks: Option<bool>,
+    executor: &Service<Executor>,
+    mut conn: Connection<Tasks>,
+) -> Response<BatchStatus> {
+    Response::invoke(async { executor.batch(&mut conn, &id, tasks.unwrap_or(false)).await }).await
+}
+
+#[get("/batch/results?<id>")]
+pub async fn batch_results(
+    id: String,
+    executor: &Service<Executor>,
+    mut conn: Connection<Tasks>,
+) -> Result<(ContentType, ByteStream![Vec<u8>]), status::Custom<String>> {
+    executor
+        .batch_results(&mut conn, &id)
+        .await
+        .map(|lines| {
+            (
+                ContentType::new("application", "x-ndjson"),
+                ByteStream::from(lines),
+            )
+        })
+        .map_err(|err| {
+            eprintln!("Failed to download batch '{}': {:?}", id, err);
+            let status = if err.downcast_ref::<BadRequest>().is_some() {
+                Status::BadRequest
+            } else {
+                Status::InternalServerError
+            };
+            status::Custom(status, format!("{:#}", err))
+        })
+}
+
+#[get("/list?<filter..>")]
+pub async fn list(
+    filter: TaskFilter,
+    executor: &Service<Executor>,
+    mut conn: Connection<Tasks>,
+) -> Response<TaskPage> {
+    Response::invoke(async { executor.list(&mut conn, &filter).await }).await
 }
 
 #[get("/query?<id>")]
@@ -28,10 +172,8 @@ pub async fn query(
     id: String,
     executor: &Service<Executor>,
     mut conn: Connection<Tasks>,
-) -> Json<Response<Option<Task>>> {
-    Response::invoke(async { executor.get(&mut conn, &id).await })
-        .await
-        .into()
+) -> Response<Option<Task>> {
+    Response::invoke(async { executor.query(&mut conn, &id).await }).await
 }
 
 #[get("/result?<id>&<timeout>")]
@@ -39,9 +181,46 @@ pub async fn result(
     id: String,
     timeout: Option<u64>,
     executor: &Service<Executor>,
-    conn: Connection<Tasks>,
-) -> Json<Response<Task>> {
-    Response::invoke(async { executor.result(conn, &id, timeout.unwrap_or(0)).await })
+) -> Response<Task> {
+    Response::invoke(async { executor.result(&id, timeout.unwrap_or(0)).await }).await
+}
+
+#[post("/cancel?<id>")]
+pub async fn cancel(
+    id: String,
+    executor: &Service<Executor>,
+    mut conn: Connection<Tasks>,
+) -> Response<Task> {
+    Response::invoke(async { executor.cancel(&mut conn, &id).await }).await
+}
+
+#[get("/stream?<id>&<last_id>")]
+pub async fn stream(
+    id: String,
+    last_id: Option<String>,
+    last_event_id: LastEventId,
+    executor: &Service<Executor>,
+) -> Result<EventStream![], Response<()>> {
+    executor
+        .progress(&id, last_id.or(last_event_id.0))
         .await
-        .into()
+        .map(|progress| {
+            EventStream::from(progress.map(|progress| {
+                Event::data(progress.data)
+                    .event(progress.event)
+                    .id(progress.id)
+            }))
+        })
+        .map_err(|err| {
+            eprintln!("Failed to stream task '{}': {:?}", id, err);
+            err.into()
+        })
+}
+
+#[get("/queues")]
+pub async fn queues(
+    executor: &Service<Executor>,
+    mut conn: Connection<Tasks>,
+) -> Response<Vec<QueueDepth>> {
+    Response::invoke(async { executor.depth(&mut conn).await }).await
 }
@@ -0,0 +1,316 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chrono::Local;
+use rocket::fairing::AdHoc;
+use rocket_db_pools::deadpool_redis::{
+    self,
+    redis::{self, AsyncCommands},
+};
+use tokio::time;
+use uuid::Uuid;
+
+use crate::{
+    databases::Tasks,
+    entities::{
+        config::ServiceConfig,
+        cron::{Cron, CronRequest},
+        datetime::DateTime,
+        response::BadRequest,
+        task::Task,
+    },
+    services::{executor::Executor, models::ModelRegistry, Inject, Service},
+};
+
+pub struct CronScheduler;
+
+impl Inject for CronScheduler {
+    fn new(_: &ServiceConfig) -> Self {
+        Self
+    }
+}
+
+static CRONS: &str = "CRONS";
+static CRON_QUEUE: &str = "CRON_QUEUE";
+static CRON_INTERVAL: Duration = Duration::from_secs(1);
+static CRON_BATCH_SIZE: isize = 100;
+
+impl CronScheduler {
+    pub fn register() -> AdHoc {
+        AdHoc::on_liftoff("Cron Scheduler", |rocket| {
+            Box::pin(async move {
+                if let Err(err) = super::configure(rocket) {
+                    eprintln!("Failed to start cron scheduler: {:?}", err);
+                    return;
+                }
+                let scheduler = Service::<CronScheduler>::inject();
+                tokio::spawn(async move {
+                    loop {
+                        if let Err(err) = scheduler.fire().await {
+                            eprintln!("Failed to fire cron schedules: {:?}", err);
+                        }
+                        time::sleep(CRON_INTERVAL).await;
+                    }
+                });
+            })
+        })
+    }
+
+    pub async fn create(
+        &self,
+        conn: &mut deadpool_redis::Connection,
+        request: CronRequest,
+    ) -> anyhow::Result<Cron> {
+        let mut cron = Cron {
+            id: Uuid::new_v4().to_string(),
+            name: None,
+            cron: String::new(),
+            template: request.chat.clone(),
+            priority: Default::default(),
+            submitter: None,
+            enabled: true,
+            next_run: None,
+            last_run: None,
+            last_task_id: None,
+            create_time: DateTime::local(),
+        };
+        self.apply(&mut cron, request)?;
+        self.save(conn, &cron).await?;
+        Ok(cron)
+    }
+
+    pub async fn update(
+        &self,
+        conn: &mut deadpool_redis::Connection,
+        cron_id: &str,
+        request: CronRequest,
+    ) -> anyhow::Result<Cron> {
+        let mut cron = self
+            .get(conn, cron_id)
+            .await?
+            .ok_or_else(|| anyhow!("Cron '{cron_id}' not existed"))?;
+        self.apply(&mut cron, request)?;
+        self.save(conn, &cron).await?;
+        Ok(cron)
+    }
+
+    pub async fn delete(
+        &self,
+        conn: &mut deadpool_redis::Connection,
+        cron_id: &str,
+    ) -> anyhow::Result<Cron> {
+        let cron = self
+            .get(conn, cron_id)
+            .await?
+            .ok_or_else(|| anyhow!("Cron '{cron_id}' not existed"))?;
+        let _: () = conn.hdel(CRONS, cron_id).await?;
+        let _: () = conn.zrem(CRON_QUEUE, cron_id).await?;
+        Ok(cron)
+    }
+
+    pub async fn get(
+        &self,
+        conn: &mut deadpool_redis::Connection,
+        cron_id: &str,
+    ) -> anyhow::Result<Option<Cron>> {
+        let value: Option<String> = conn.hget(CRONS, cron_id).await?;
+        Ok(value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()?)
+    }
+
+    pub async fn list(&self, conn: &mut deadpool_redis::Connection) -> anyhow::Result<Vec<Cron>> {
+        let values: Vec<String> = conn.hvals(CRONS).await?;
+        let mut crons = values
+            .iter()
+            .map(|value| serde_json::from_str::<Cron>(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        crons.sort_by_key(|cron| cron.create_time.timestamp());
+        Ok(crons)
+    }
+
+    fn apply(&self, cron: &mut Cron, request: CronRequest) -> anyhow::Result<()> {
+        let CronRequest {
+            chat,
+            cron: expression,
+            name,
+            priority,
+            submitter,
+            enabled,
+        } = request;
+        let registry = Service::<ModelRegistry>::inject();
+        let prompt = chat.message.clone().into();
+        registry.select(chat.model.as_deref(), &prompt)?;
+        cron.next_run = self.next_run(&expression)?;
+        cron.cron = expression;
+        cron.template = chat;
+        cron.name = name;
+        cron.priority = priority.unwrap_or_default();
+        cron.submitter = submitter;
+        cron.enabled = enabled.unwrap_or(true);
+        Ok(())
+    }
+
+    async fn save(&self, conn: &mut deadpool_redis::Connection, cron: &Cron) -> anyhow::Result<()> {
+        let _: () = conn
+            .hset(CRONS, &cron.id, serde_json::to_string(cron)?)
+            .await?;
+        match &cron.next_run {
+            Some(next_run) if cron.enabled => {
+                let _: () = conn
+                    .zadd(CRON_QUEUE, &cron.id, next_run.timestamp())
+                    .await?;
+            }
+            _ => {
+                let _: () = conn.zrem(CRON_QUEUE, &cron.id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn next_run(&self, expression: &str) -> anyhow::Result<Option<DateTime<Local>>> {
+        // Plain crontab expressions have no seconds field.
+        let normalized = match expression.split_whitespace().count() {
+            5 => format!("0 {expression}"),
+            _ => expression.to_owned(),
+        };
+        let schedule = ::cron::Schedule::from_str(&normalized)
+            .map_err(|err| BadRequest(format!("Invalid cron '{expression}': {err}")))?;
+        Ok(schedule.after(&Local::now()).next().map(DateTime::from))
+    }
+
+    async fn fire(&self) -> anyhow::Result<()> {
+        let mut conn = Tasks::connect().await?;
+        let now = DateTime::local().timestamp();
+        let cron_ids: Vec<String> = conn
+            .zrangebyscore_limit(CRON_QUEUE, "-inf", now, 0, CRON_BATCH_SIZE)
+            .await?;
+        for cron_id in cron_ids {
+            if let Err(err) = self.fire_one(&mut conn, &cron_id).await {
+                eprintln!("Failed to fire cron '{cron_id}': {:?}", err);
+            }
+        }
+        Ok(())
+    }
+
+    async fn fire_one(
+        &self,
+        conn: &mut deadpool_redis::Connection,
+        cron_id: &str,
+    ) -> anyhow::Result<()> {
+        let Some(cron) = self.get(conn, cron_id).await? else {
+            let _: () = conn.zrem(CRON_QUEUE, cron_id).await?;
+            return Ok(());
+        };
+        let next_run = if cron.enabled {
+            self.next_run(&cron.cron).unwrap_or_else(|err| {
+                eprintln!("Failed to schedule cron '{cron_id}': {:?}", err);
+                None
+            })
+        } else {
+            None
+        };
+        // Claiming this run and queueing the next one is a single command, so only the
+        // instance that moves the score fires and the cron is never left unscheduled.
+        let claimed: i64 = match &next_run {
+            Some(next_run) => {
+                redis::cmd("ZADD")
+                    .arg(CRON_QUEUE)
+                    .arg("XX")
+                    .arg("GT")
+                    .arg("CH")
+                    .arg(next_run.timestamp())
+                    .arg(cron_id)
+                    .query_async(&mut **conn)
+                    .await?
+            }
+            None => conn.zrem(CRON_QUEUE, cron_id).await?,
+        };
+        if claimed == 0 || !cron.enabled {
+            return Ok(());
+        }
+        let task_id = match self.materialize(conn, &cron).await {
+            Ok(task_id) => Some(task_id),
+            Err(err) => {
+                eprintln!("Failed to materialize cron '{cron_id}': {:?}", err);
+                None
+            }
+        };
+        self.record_run(&cron, next_run, task_id).await
+    }
+
+    async fn record_run(
+        &self,
+        fired: &Cron,
+        next_run: Option<DateTime<Local>>,
+        task_id: Option<String>,
+    ) -> anyhow::Result<()> {
+        let last_run = DateTime::local();
+        // WATCH needs a connection of its own. Only the run fields are written back onto
+        // the current document, so a concurrent update or delete is never undone.
+        let mut conn = Tasks::dedicated().await?;
+        loop {
+            let _: () = redis::cmd("WATCH")
+                .arg(CRONS)
+                .query_async(&mut conn)
+                .await?;
+            let value: Option<String> = conn.hget(CRONS, &fired.id).await?;
+            let Some(value) = value else {
+                let _: () = redis::cmd("UNWATCH").query_async(&mut conn).await?;
+                return Ok(());
+            };
+            let mut cron: Cron = serde_json::from_str(&value)?;
+            cron.last_run = Some(last_run.clone());
+            if let Some(task_id) = &task_id {
+                cron.last_task_id = Some(task_id.clone());
+            }
+            if cron.enabled && cron.cron == fired.cron {
+                cron.next_run = next_run.clone();
+            }
+            let committed: Option<()> = redis::pipe()
+                .atomic()
+                .hset(CRONS, &cron.id, serde_json::to_string(&cron)?)
+                .ignore()
+                .query_async(&mut conn)
+                .await?;
+            if committed.is_some() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn materialize(
+        &self,
+        conn: &mut deadpool_redis::Connection,
+        cron: &Cron,
+    ) -> anyhow::Result<String> {
+        let registry = Service::<ModelRegistry>::inject();
+        let executor = Service::<Executor>::inject();
+        let mut task = Task::create(cron.template.clone());
+        let model = registry.select(task.model.as_deref(), &task.prompt)?;
+        task.model = Some(model.name().to_owned());
+        task.options = model.resolve(Some(task.options));
+        task.priority = cron.priority;
+        task.submitter = cron.submitter.clone();
+        task.cron_id = Some(cron.id.clone());
+        executor.submit(conn, &task).await?;
+        Ok(task.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CronScheduler;
+    use chrono::Timelike;
+
+    #[test]
+    fn test_next_run() {
+        for expression in ["*/5 * * * *", "0 */5 * * * *"] {
+            let next_run = CronScheduler.next_run(expression).unwrap().unwrap();
+            assert_eq!(next_run.second(), 0);
+            assert_eq!(next_run.minute() % 5, 0);
+        }
+        assert!(CronScheduler.next_run("* * *").is_err());
+    }
+}
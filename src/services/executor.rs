@@ -1,21 +1,41 @@
 use crate::databases::Tasks;
 use crate::entities::config::{ExecutorConfig, ServiceConfig};
 use crate::entities::datetime::DateTime;
-use crate::entities::task::{Status, Task};
-use crate::services::models::{Qwen3, Qwen3VL};
+use crate::entities::oss::ObjectMeta;
+use crate::entities::response::{BadRequest, NotFound};
+use crate::entities::task::{
+    BatchStatus, DeadLetter, Priority, Progress, QueueDepth, Status, Task, TaskFilter, TaskPage,
+    TaskSummary,
+};
+use crate::services::models::ModelRegistry;
+use crate::services::oss::{Storage, ARCHIVE_PREFIX};
+use crate::services::webhook::Webhook;
 use crate::services::{Inject, Service};
 use agentx::Completion;
 use anyhow::anyhow;
 use async_compression::tokio::write::{ZstdDecoder, ZstdEncoder};
-use futures::StreamExt;
-use rocket_db_pools::deadpool_redis::redis::AsyncCommands;
+use async_stream::stream;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use regex::Regex;
+use rocket::fairing::AdHoc;
+use rocket_db_pools::deadpool_redis::redis::aio::{ConnectionLike, MultiplexedConnection};
+use rocket_db_pools::deadpool_redis::redis::{self, AsyncCommands, Direction};
 use rocket_db_pools::Connection;
+use serde_json::{json, Value};
 use state::InitCell;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once};
 use std::time::{Duration, Instant};
 use tokio::io::AsyncWriteExt;
-use tokio::sync::Semaphore;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 use tokio::time;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct Executor {
@@ -30,171 +50,1609 @@ impl Inject for Executor {
     }
 }
 
-static SEMAPHORE: InitCell<Arc<Semaphore>> = InitCell::new();
 static PENDING_QUEUE: &str = "PENDING_QUEUE";
+static HIGH_PRIORITY_QUEUE: &str = "PENDING_QUEUE:high";
+static LOW_PRIORITY_QUEUE: &str = "PENDING_QUEUE:low";
+static POLL_INTERVAL: Duration = Duration::from_secs(1);
+// A zero timeout would skip the blocking wait and spin on the queues.
+static MIN_CONSUME_TIMEOUT: u64 = 1;
+static ROUNDS: AtomicU64 = AtomicU64::new(0);
+static PROCESSING_PREFIX: &str = "PROCESSING_QUEUE:";
+static HEARTBEAT_PREFIX: &str = "HEARTBEAT:";
+static WORKERS: &str = "WORKERS";
+static REAPER_LOCK: &str = "REAPER_LOCK";
+static CANCEL_PREFIX: &str = "CANCEL:";
+static COMPLETION_PREFIX: &str = "COMPLETION:";
+static PROGRESS_PREFIX: &str = "PROGRESS:";
+static PROGRESS_BATCH_SIZE: usize = 100;
+static PROGRESS_BLOCK: Duration = Duration::from_secs(5);
+static BATCH_PREFIX: &str = "BATCH:";
+static BATCH_CHUNK_SIZE: usize = 100;
+static INDEX_PREFIX: &str = "INDEX:";
+static INDEX_ALL: &str = "INDEX:all";
+static INDEX_EXPIRY: &str = "INDEX_EXPIRY";
+static INDEX_TRIM_SIZE: usize = 100;
+static TRIM_INDEX_SCRIPT: &str = r"
+local expired = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, ARGV[2])
+for _, id in ipairs(expired) do
+    for _, key in ipairs(KEYS) do
+        redis.call('ZREM', key, id)
+    end
+end
+return #expired
+";
+static PROMOTE_SCRIPT: &str = r"
+if redis.call('ZREM', KEYS[1], ARGV[1]) == 0 then
+    return 0
+end
+if tonumber(ARGV[3]) > 0 then
+    redis.call('SET', KEYS[2], ARGV[2], 'EX', ARGV[3])
+else
+    redis.call('SET', KEYS[2], ARGV[2])
+end
+redis.call('LPUSH', KEYS[3], ARGV[1])
+return 1
+";
+static DEFAULT_LIST_LIMIT: usize = 20;
+static MAX_LIST_LIMIT: usize = 100;
+static LIST_SCAN_SIZE: usize = 200;
+static MAX_LIST_ROUNDS: usize = 50;
+static SCHEDULED_QUEUE: &str = "SCHEDULED_QUEUE";
+static SCHEDULE_INTERVAL: Duration = Duration::from_secs(1);
+static SCHEDULE_BATCH_SIZE: isize = 100;
+static CHECK_INTERVAL: Duration = Duration::from_secs(1);
+static TRANSIENT_PATTERN: InitCell<Regex> = InitCell::new();
+static ARCHIVE_RETRIES: u32 = 3;
+static DEAD_LETTERS: &str = "DEAD_LETTERS";
+static DEAD_LETTER_QUEUE: &str = "DEAD_LETTER_QUEUE";
+static DEAD_LETTER_TRIM_SIZE: usize = 100;
+static TRIM_DEAD_LETTERS_SCRIPT: &str = r"
+local expired = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, ARGV[2])
+for _, id in ipairs(expired) do
+    redis.call('ZREM', KEYS[1], id)
+    redis.call('HDEL', KEYS[2], id)
+end
+return #expired
+";
+static SHUTDOWN: InitCell<CancellationToken> = InitCell::new();
+static HANDLES: Mutex<Vec<(String, JoinHandle<()>)>> = Mutex::new(Vec::new());
+static WAITERS: InitCell<Mutex<HashMap<String, broadcast::Sender<()>>>> = InitCell::new();
+static LISTENER: Once = Once::new();
 
 impl Executor {
-    pub async fn submit(&self, mut conn: Connection<Tasks>, task: &Task) -> anyhow::Result<()> {
-        self.set(&mut conn, task).await?;
-        let _: () = conn.lpush(PENDING_QUEUE, &task.id).await?;
-        let semaphore = SEMAPHORE.get_or_init(|| Arc::new(Semaphore::new(self.config.num_workers)));
-        if let Ok(permit) = semaphore.try_acquire() {
-            let executor = self.clone();
-            tokio::spawn(async move {
-                loop {
-                    match executor.consume(&mut conn).await {
-                        Ok(Some(task)) => {
-                            if let Err(err) = executor.execute(&mut conn, task).await {
+    pub async fn submit(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task: &Task,
+    ) -> anyhow::Result<()> {
+        if task.status == Status::Scheduled {
+            self.schedule(conn, task).await
+        } else {
+            self.enqueue(conn, task).await
+        }
+    }
+
+    pub async fn submit_batch(
+        &self,
+        conn: &mut MultiplexedConnection,
+        batch_id: &str,
+        tasks: &[Task],
+    ) -> anyhow::Result<()> {
+        let key = format!("{BATCH_PREFIX}{batch_id}");
+        let task_ids = tasks.iter().map(|task| &task.id).collect::<Vec<_>>();
+        // One transaction, so a failed submission leaves no partial batch behind.
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        pipe.rpush(&key, task_ids).ignore();
+        let ttl = tasks
+            .iter()
+            .map(|task| self.retention(task))
+            .collect::<Option<Vec<_>>>()
+            .and_then(|ttls| ttls.into_iter().max());
+        if let Some(ttl) = ttl {
+            pipe.expire(&key, ttl as i64).ignore();
+        }
+        for task in tasks {
+            let value = self.compress(serde_json::to_string(task)?).await?;
+            self.stage_raw(&mut pipe, task, value);
+            self.stage_index(&mut pipe, task);
+            if task.status == Status::Scheduled {
+                let run_at = task
+                    .run_at
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Task '{}' has no run time", task.id))?;
+                pipe.zadd(SCHEDULED_QUEUE, &task.id, run_at.timestamp())
+                    .ignore();
+            } else {
+                pipe.lpush(self.queue(&task.priority), &task.id).ignore();
+            }
+        }
+        let _: () = pipe.query_async(&mut *conn).await?;
+        Ok(())
+    }
+
+    async fn schedule(&self, conn: &mut MultiplexedConnection, task: &Task) -> anyhow::Result<()> {
+        let run_at = task
+            .run_at
+            .as_ref()
+            .ok_or_else(|| anyhow!("Task '{}' has no run time", task.id))?;
+        self.set(conn, task).await?;
+        let _: () = conn
+            .zadd(SCHEDULED_QUEUE, &task.id, run_at.timestamp())
+            .await?;
+        Ok(())
+    }
+
+    pub fn scheduler() -> AdHoc {
+        AdHoc::on_liftoff("Task Scheduler", |rocket| {
+            Box::pin(async move {
+                if let Err(err) = super::configure(rocket) {
+                    eprintln!("Failed to start scheduler: {:?}", err);
+                    return;
+                }
+                let executor = Service::<Executor>::inject();
+                tokio::spawn(async move {
+                    loop {
+                        if let Err(err) = executor.promote().await {
+                            eprintln!("Failed to promote scheduled tasks: {:?}", err);
+                        }
+                        time::sleep(SCHEDULE_INTERVAL).await;
+                    }
+                });
+            })
+        })
+    }
+
+    async fn promote(&self) -> anyhow::Result<()> {
+        let mut conn = Tasks::connect().await?;
+        let now = DateTime::local().timestamp();
+        let task_ids: Vec<String> = conn
+            .zrangebyscore_limit(SCHEDULED_QUEUE, "-inf", now, 0, SCHEDULE_BATCH_SIZE)
+            .await?;
+        for task_id in task_ids {
+            if let Err(err) = self.promote_one(&mut conn, &task_id).await {
+                eprintln!("Failed to promote task '{task_id}': {:?}", err);
+            }
+        }
+        Ok(())
+    }
+
+    async fn promote_one(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task_id: &str,
+    ) -> anyhow::Result<()> {
+        let value: Option<Vec<u8>> = conn.get(task_id).await?;
+        let Some(value) = value else {
+            let _: () = conn.zrem(SCHEDULED_QUEUE, task_id).await?;
+            return Ok(());
+        };
+        let mut task = match self.decode(&value).await {
+            Ok(task) => task,
+            Err(err) => {
+                // An unreadable task would otherwise be retried on every round.
+                let removed: i64 = conn.zrem(SCHEDULED_QUEUE, task_id).await?;
+                if removed > 0 {
+                    self.dead_letter(conn, task_id, format!("{:#}", err))
+                        .await?;
+                }
+                return Ok(());
+            }
+        };
+        if task.status != Status::Scheduled {
+            let _: () = conn.zrem(SCHEDULED_QUEUE, task_id).await?;
+            return Ok(());
+        }
+        task.status = Status::Pending;
+        let value = self.compress(serde_json::to_string(&task)?).await?;
+        // Only the instance that wins the ZREM promotes the task, and it stores
+        // and queues it in the same step so a crash cannot drop it in between.
+        let promoted: i64 = redis::cmd("EVAL")
+            .arg(PROMOTE_SCRIPT)
+            .arg(3)
+            .arg(SCHEDULED_QUEUE)
+            .arg(&task.id)
+            .arg(self.queue(&task.priority))
+            .arg(&task.id)
+            .arg(value)
+            .arg(self.ttl(&task).unwrap_or(0))
+            .query_async(&mut *conn)
+            .await?;
+        if promoted > 0 {
+            self.index(conn, &task).await?;
+        }
+        Ok(())
+    }
+
+    async fn enqueue(&self, conn: &mut MultiplexedConnection, task: &Task) -> anyhow::Result<()> {
+        self.set(conn, task).await?;
+        let _: () = conn.lpush(self.queue(&task.priority), &task.id).await?;
+        Ok(())
+    }
+
+    pub fn workers() -> AdHoc {
+        AdHoc::on_liftoff("Task Workers", |rocket| {
+            Box::pin(async move {
+                if let Err(err) = super::configure(rocket) {
+                    eprintln!("Failed to start workers: {:?}", err);
+                    return;
+                }
+                let executor = Service::<Executor>::inject();
+                let mut handles = HANDLES.lock().unwrap();
+                for _ in 0..executor.config.num_workers {
+                    let worker = Uuid::new_v4().to_string();
+                    let handle = tokio::spawn(executor.work(worker.clone()));
+                    handles.push((worker, handle));
+                }
+            })
+        })
+    }
+
+    pub fn shutdown() -> AdHoc {
+        AdHoc::on_shutdown("Task Workers Shutdown", |rocket| {
+            Box::pin(async move {
+                if let Err(err) = super::configure(rocket) {
+                    eprintln!("Failed to shutdown workers: {:?}", err);
+                    return;
+                }
+                Service::<Executor>::inject().drain().await;
+            })
+        })
+    }
+
+    async fn drain(&self) {
+        SHUTDOWN.get_or_init(CancellationToken::new).cancel();
+        let handles = std::mem::take(&mut *HANDLES.lock().unwrap());
+        let deadline = time::Instant::now() + Duration::from_secs(self.config.grace_period);
+        let mut lost = Vec::new();
+        for (worker, mut handle) in handles {
+            if time::timeout_at(deadline, &mut handle).await.is_err() {
+                handle.abort();
+                let _ = handle.await;
+                lost.push(worker);
+            }
+        }
+        if lost.is_empty() {
+            return;
+        }
+        let mut conn = match Tasks::connect().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                eprintln!("Failed to requeue tasks on shutdown: {:?}", err);
+                return;
+            }
+        };
+        for worker in lost {
+            eprintln!(
+                "Worker '{worker}' did not finish within {}s grace period",
+                self.config.grace_period
+            );
+            if let Err(err) = self.requeue(&mut conn, &worker).await {
+                eprintln!("Failed to requeue tasks of worker '{worker}': {:?}", err);
+            }
+        }
+    }
+
+    async fn requeue(&self, conn: &mut MultiplexedConnection, worker: &str) -> anyhow::Result<()> {
+        let processing = format!("{PROCESSING_PREFIX}{worker}");
+        let task_ids: Vec<String> = conn.lrange(&processing, 0, -1).await?;
+        for task_id in task_ids {
+            let Some(mut task) = self.get(conn, &task_id).await? else {
+                continue;
+            };
+            if task.status.is_completed() {
+                continue;
+            }
+            eprintln!("Task '{task_id}' requeued on shutdown of worker '{worker}'");
+            task.status = Status::Pending;
+            self.set(conn, &task).await?;
+            let _: () = conn.rpush(self.queue(&task.priority), &task_id).await?;
+        }
+        let _: () = conn.del(&processing).await?;
+        let _: () = conn.srem(WORKERS, worker).await?;
+        self.unregister(conn, worker).await
+    }
+
+    fn is_shutdown(&self) -> bool {
+        SHUTDOWN
+            .try_get()
+            .is_some_and(|shutdown| shutdown.is_cancelled())
+    }
+
+    async fn work(&self, worker: String) {
+        let beating = CancellationToken::new();
+        let _beating = beating.clone().drop_guard();
+        tokio::spawn(self.clone().beat(worker.clone(), beating.clone()));
+        while !self.is_shutdown() {
+            // Workers block on BLMOVE, so they keep their own connection out of the pool.
+            let mut conn = match Tasks::dedicated().await {
+                Ok(conn) => conn,
+                Err(err) => {
+                    eprintln!("Failed to connect worker '{worker}': {:?}", err);
+                    time::sleep(POLL_INTERVAL).await;
+                    continue;
+                }
+            };
+            if let Err(err) = self.recover(&mut conn, &worker).await {
+                eprintln!("Failed to recover tasks of worker '{worker}': {:?}", err);
+                time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+            while !self.is_shutdown() {
+                match self.consume(&mut conn, &worker).await {
+                    Ok(Some(task)) => {
+                        let task_id = task.id.clone();
+                        match self.execute(&mut conn, task).await {
+                            Ok(()) => {
+                                if let Err(err) = self.ack(&mut conn, &worker, &task_id).await {
+                                    eprintln!("Failed to ack: {:?}", err);
+                                }
+                            }
+                            Err(err) => {
                                 eprintln!("Failed to execute: {:?}", err);
+                                if self.is_shutdown() {
+                                    continue;
+                                }
+                                if let Err(err) = self.release(&mut conn, &worker, &task_id).await {
+                                    eprintln!("Failed to release task '{task_id}': {:?}", err);
+                                    break;
+                                }
                             }
                         }
-                        Ok(None) => break,
-                        Err(err) => eprintln!("Failed to consume: {:?}", err),
+                    }
+                    Ok(None) => (),
+                    Err(err) => {
+                        eprintln!("Failed to consume: {:?}", err);
+                        break;
                     }
                 }
-                drop(permit);
-            });
+            }
+            if self.is_shutdown() {
+                beating.cancel();
+                if let Err(err) = self.requeue(&mut conn, &worker).await {
+                    eprintln!("Failed to requeue tasks of worker '{worker}': {:?}", err);
+                }
+                break;
+            }
+            time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn beat(self, worker: String, beating: CancellationToken) {
+        let mut interval = time::interval(Duration::from_secs((self.config.heartbeat / 3).max(1)));
+        loop {
+            tokio::select! {
+                _ = beating.cancelled() => break,
+                _ = interval.tick() => (),
+            }
+            let result = match Tasks::connect().await {
+                Ok(mut conn) => self.heartbeat(&mut conn, &worker).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                eprintln!("Failed to send heartbeat of worker '{worker}': {:?}", err);
+            }
+        }
+    }
+
+    async fn recover(&self, conn: &mut MultiplexedConnection, worker: &str) -> anyhow::Result<()> {
+        let processing = format!("{PROCESSING_PREFIX}{worker}");
+        let task_ids: Vec<String> = conn.lrange(&processing, 0, -1).await?;
+        for task_id in task_ids {
+            self.release(conn, worker, &task_id).await?;
         }
         Ok(())
     }
 
-    async fn consume(&self, conn: &mut Connection<Tasks>) -> anyhow::Result<Option<Task>> {
-        if let Some((_, task_id)) = conn
-            .brpop::<&str, Option<((), String)>>(PENDING_QUEUE, self.config.timeout as f64)
-            .await?
-        {
-            if let Some(task) = self.get(conn, &task_id).await? {
-                Ok(Some(task))
-            } else {
-                Err(anyhow!("Task '{task_id}' not existed"))
+    async fn release(
+        &self,
+        conn: &mut MultiplexedConnection,
+        worker: &str,
+        task_id: &str,
+    ) -> anyhow::Result<()> {
+        match self.get(conn, task_id).await {
+            Ok(Some(task)) if !task.status.is_completed() => {
+                self.reclaim(conn, task, "Execution failed").await?
+            }
+            Ok(_) => (),
+            Err(err) => {
+                self.dead_letter(conn, task_id, format!("Invalid payload: {:#}", err))
+                    .await?
+            }
+        }
+        self.ack(conn, worker, task_id).await
+    }
+
+    async fn reclaim(
+        &self,
+        conn: &mut MultiplexedConnection,
+        mut task: Task,
+        reason: &str,
+    ) -> anyhow::Result<()> {
+        if task.requeues >= self.config.max_requeues {
+            eprintln!("Task '{}' failed after {} requeues", task.id, task.requeues);
+            task.status = Status::Failed;
+            task.err_msg = Some(format!("{reason} after {} requeues", task.requeues));
+            task.finish_time = Some(DateTime::local());
+            self.set(conn, &task).await?;
+            self.complete(conn, &task).await?;
+            self.dead_letter(conn, &task.id, task.err_msg.clone().unwrap_or_default())
+                .await
+        } else {
+            eprintln!("Task '{}' requeued: {reason}", task.id);
+            task.status = Status::Pending;
+            task.requeues += 1;
+            self.set(conn, &task).await?;
+            let _: () = conn.rpush(self.queue(&task.priority), &task.id).await?;
+            Ok(())
+        }
+    }
+
+    async fn consume(
+        &self,
+        conn: &mut MultiplexedConnection,
+        worker: &str,
+    ) -> anyhow::Result<Option<Task>> {
+        if let Err(err) = self.reap(conn).await {
+            eprintln!("Failed to reap: {:?}", err);
+        }
+        let processing = format!("{PROCESSING_PREFIX}{worker}");
+        let mut priorities = Priority::all();
+        let interval = self.config.starvation_interval.max(1);
+        if ROUNDS.fetch_add(1, Ordering::Relaxed) % interval == interval - 1 {
+            priorities.reverse();
+        }
+        let now = Instant::now();
+        let task_id = loop {
+            let mut task_id: Option<String> = None;
+            for priority in &priorities {
+                task_id = conn
+                    .lmove(
+                        self.queue(priority),
+                        processing.as_str(),
+                        Direction::Right,
+                        Direction::Left,
+                    )
+                    .await?;
+                if task_id.is_some() {
+                    break;
+                }
+            }
+            if task_id.is_some()
+                || self.is_shutdown()
+                || now.elapsed().as_secs() >= self.config.timeout.max(MIN_CONSUME_TIMEOUT)
+            {
+                break task_id;
+            }
+            task_id = conn
+                .blmove(
+                    HIGH_PRIORITY_QUEUE,
+                    processing.as_str(),
+                    Direction::Right,
+                    Direction::Left,
+                    POLL_INTERVAL.as_secs_f64(),
+                )
+                .await?;
+            if task_id.is_some() {
+                break task_id;
+            }
+        };
+        if let Some(task_id) = task_id {
+            match self.get(conn, &task_id).await {
+                Ok(Some(task)) => Ok(Some(task)),
+                Ok(None) => {
+                    let _: () = conn.lrem(&processing, 0, &task_id).await?;
+                    Err(anyhow!("Task '{task_id}' not existed"))
+                }
+                Err(err) => {
+                    eprintln!("Task '{task_id}' has invalid payload: {:?}", err);
+                    let _: () = conn.lrem(&processing, 0, &task_id).await?;
+                    self.dead_letter(conn, &task_id, format!("Invalid payload: {:#}", err))
+                        .await?;
+                    Ok(None)
+                }
             }
         } else {
             Ok(None)
         }
     }
 
-    async fn execute(&self, conn: &mut Connection<Tasks>, mut task: Task) -> anyhow::Result<()> {
+    fn queue(&self, priority: &Priority) -> &'static str {
+        match priority {
+            Priority::High => HIGH_PRIORITY_QUEUE,
+            Priority::Normal => PENDING_QUEUE,
+            Priority::Low => LOW_PRIORITY_QUEUE,
+        }
+    }
+
+    pub async fn depth(&self, conn: &mut MultiplexedConnection) -> anyhow::Result<Vec<QueueDepth>> {
+        let mut depths = Vec::new();
+        for priority in Priority::all() {
+            let depth: usize = conn.llen(self.queue(&priority)).await?;
+            depths.push(QueueDepth { priority, depth });
+        }
+        Ok(depths)
+    }
+
+    async fn ack(
+        &self,
+        conn: &mut MultiplexedConnection,
+        worker: &str,
+        task_id: &str,
+    ) -> anyhow::Result<()> {
+        let _: () = conn
+            .lrem(format!("{PROCESSING_PREFIX}{worker}"), 1, task_id)
+            .await?;
+        Ok(())
+    }
+
+    async fn heartbeat(
+        &self,
+        conn: &mut MultiplexedConnection,
+        worker: &str,
+    ) -> anyhow::Result<()> {
+        let _: () = conn.sadd(WORKERS, worker).await?;
+        let _: () = conn
+            .set_ex(
+                format!("{HEARTBEAT_PREFIX}{worker}"),
+                1,
+                self.config.heartbeat,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn unregister(
+        &self,
+        conn: &mut MultiplexedConnection,
+        worker: &str,
+    ) -> anyhow::Result<()> {
+        let _: () = conn.del(format!("{HEARTBEAT_PREFIX}{worker}")).await?;
+        Ok(())
+    }
+
+    async fn reap(&self, conn: &mut MultiplexedConnection) -> anyhow::Result<()> {
+        let locked: Option<String> = redis::cmd("SET")
+            .arg(REAPER_LOCK)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(self.config.heartbeat)
+            .query_async(&mut *conn)
+            .await?;
+        if locked.is_none() {
+            return Ok(());
+        }
+        let workers: Vec<String> = conn.smembers(WORKERS).await?;
+        for worker in workers {
+            if conn.exists(format!("{HEARTBEAT_PREFIX}{worker}")).await? {
+                continue;
+            }
+            let processing = format!("{PROCESSING_PREFIX}{worker}");
+            let task_ids: Vec<String> = conn.lrange(&processing, 0, -1).await?;
+            for task_id in task_ids {
+                let task = match self.get(conn, &task_id).await {
+                    Ok(Some(task)) => task,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        let reason = format!("Invalid payload: {:#}", err);
+                        self.dead_letter(conn, &task_id, reason).await?;
+                        continue;
+                    }
+                };
+                match task.status {
+                    Status::Pending | Status::Running => (),
+                    _ => continue,
+                }
+                self.reclaim(conn, task, "Worker lost").await?;
+            }
+            let _: () = conn.del(&processing).await?;
+            let _: () = conn.srem(WORKERS, &worker).await?;
+        }
+        Ok(())
+    }
+
+    async fn execute(
+        &self,
+        conn: &mut MultiplexedConnection,
+        mut task: Task,
+    ) -> anyhow::Result<()> {
         task.status = Status::Running;
-        self.set(conn, &task).await?;
-        let result = if task.prompt.is_media() {
-            let model = Service::<Qwen3VL>::inject();
-            model.stream(&task.prompt).await
-        } else {
-            let model = Service::<Qwen3>::inject();
-            model.stream(&task.prompt).await
-        };
-        match result {
-            Ok(mut stream) => {
-                task.status = Status::Finished;
-                task.finish_time = Some(DateTime::local());
-                let mut encoder = ZstdEncoder::new(Vec::new());
-                let json = serde_json::to_string(&task)?;
-                let (partial, _) = json.rsplit_once("}").unwrap();
-                encoder.write(partial.as_bytes()).await?;
+        let mut retries = 0;
+        loop {
+            // Checked before every attempt, a cancel during the backoff must not start another.
+            if self.is_cancelled(conn, &task.id).await? {
+                return self.set_cancelled(conn, &mut task).await;
+            }
+            task.attempts += 1;
+            self.set(conn, &task).await?;
+            match self.run(conn, &task).await {
+                Ok(true) => {
+                    task.status = Status::Finished;
+                    return self.complete(conn, &task).await;
+                }
+                Ok(false) => return self.set_cancelled(conn, &mut task).await,
+                Err(err) => {
+                    // Mid-stream failures discard the partial output and restart the attempt.
+                    task.last_error = Some(format!("{:#}", err));
+                    if self.is_shutdown() && self.is_transient(&err) {
+                        // Leave the task in the processing list so shutdown requeues it.
+                        self.set(conn, &task).await?;
+                        return Err(err);
+                    }
+                    if retries < self.config.max_retries && self.is_transient(&err) {
+                        retries += 1;
+                        let backoff = self.backoff(retries);
+                        eprintln!(
+                            "Task '{}' attempt {} failed, retrying in {:?}: {:#}",
+                            task.id, task.attempts, backoff, err
+                        );
+                        time::sleep(backoff).await;
+                        continue;
+                    }
+                    task.status = Status::Failed;
+                    task.err_msg = Some(err.to_string());
+                    task.finish_time = Some(DateTime::local());
+                    self.set(conn, &task).await?;
+                    self.complete(conn, &task).await?;
+                    return self.dead_letter(conn, &task.id, format!("{:#}", err)).await;
+                }
+            }
+        }
+    }
+
+    async fn run(&self, conn: &mut MultiplexedConnection, task: &Task) -> anyhow::Result<bool> {
+        let registry = Service::<ModelRegistry>::inject();
+        let model = registry.select(task.model.as_deref(), &task.prompt)?;
+        let mut stream = model.stream(&task.prompt, &task.options).await?;
+        self.append(conn, task, "start", json!({ "attempts": task.attempts }))
+            .await?;
+        let mut finished = task.clone();
+        finished.status = Status::Finished;
+        finished.finish_time = Some(DateTime::local());
+        let mut encoder = ZstdEncoder::new(Vec::new());
+        let json = serde_json::to_string(&finished)?;
+        let (partial, _) = json.rsplit_once("}").unwrap();
+        encoder.write(partial.as_bytes()).await?;
+        encoder
+            .write(",\"completion\":{\"reasoning_content\":\"".as_bytes())
+            .await?;
+        let mut reasoning = true;
+        let mut usage_encoded = None;
+        let mut checked = Instant::now();
+        while let Some(chunk) = stream.next().await {
+            if checked.elapsed() >= CHECK_INTERVAL {
+                if self.is_cancelled(conn, &task.id).await? {
+                    return Ok(false);
+                }
+                checked = Instant::now();
+            }
+            let Completion {
+                reasoning_content,
+                content,
+                usage,
+            } = chunk;
+            if let Some(reasoning_content) = reasoning_content {
                 encoder
-                    .write(",\"completion\":{\"reasoning_content\":\"".as_bytes())
+                    .write(self.escape(&reasoning_content)?.as_bytes())
                     .await?;
-                let mut reasoning = true;
-                let mut usage_encoded = None;
-                while let Some(chunk) = stream.next().await {
-                    let Completion {
-                        reasoning_content,
-                        content,
-                        usage,
-                    } = chunk;
-                    if let Some(reasoning_content) = reasoning_content {
-                        encoder
-                            .write(self.escape(&reasoning_content).as_bytes())
-                            .await?;
-                    }
-                    if let Some(content) = content {
-                        if reasoning {
-                            encoder.write("\",\"content\":\"".as_bytes()).await?;
-                            reasoning = false;
-                        }
-                        encoder.write(self.escape(&content).as_bytes()).await?;
+                self.append(
+                    conn,
+                    task,
+                    "reasoning",
+                    json!({ "reasoning_content": reasoning_content }),
+                )
+                .await?;
+            }
+            if let Some(content) = content {
+                if reasoning {
+                    encoder.write("\",\"content\":\"".as_bytes()).await?;
+                    reasoning = false;
+                }
+                encoder.write(self.escape(&content)?.as_bytes()).await?;
+                self.append(conn, task, "content", json!({ "content": content }))
+                    .await?;
+            }
+            if let Some(usage) = usage {
+                self.append(conn, task, "usage", serde_json::to_value(&usage)?)
+                    .await?;
+                usage_encoded = Some(usage)
+            }
+        }
+        if let Some(usage) = usage_encoded {
+            encoder.write("\",\"usage\":".as_bytes()).await?;
+            encoder
+                .write(serde_json::to_string(&usage)?.as_bytes())
+                .await?;
+        } else {
+            encoder.write("\",\"usage\":null".as_bytes()).await?;
+        }
+        encoder.write("}}".as_bytes()).await?;
+        encoder.shutdown().await?;
+        self.set_raw(conn, &finished, encoder.into_inner()).await?;
+        self.index(conn, &finished).await?;
+        Ok(true)
+    }
+
+    async fn complete(&self, conn: &mut MultiplexedConnection, task: &Task) -> anyhow::Result<()> {
+        let _: () = conn
+            .publish(
+                format!("{COMPLETION_PREFIX}{}", task.id),
+                serde_json::to_string(&task.status)?,
+            )
+            .await?;
+        self.append(conn, task, "done", json!({ "status": task.status }))
+            .await?;
+        if task.status == Status::Finished && self.config.retention.archive {
+            let executor = self.clone();
+            let task_id = task.id.clone();
+            tokio::spawn(async move {
+                let mut attempts = 0;
+                while let Err(err) = executor.archive(&task_id).await {
+                    attempts += 1;
+                    eprintln!("Failed to archive task '{task_id}': {:?}", err);
+                    if attempts > ARCHIVE_RETRIES || err.downcast_ref::<NotFound>().is_some() {
+                        break;
                     }
-                    if let Some(usage) = usage {
-                        usage_encoded = Some(usage)
+                    time::sleep(executor.backoff(attempts)).await;
+                }
+            });
+        }
+        if task.callback_url.is_some() && task.status != Status::Cancelled {
+            let task = match task.status {
+                Status::Finished => self.get(conn, &task.id).await?,
+                _ => Some(task.clone()),
+            };
+            if let Some(task) = task {
+                Service::<Webhook>::inject().dispatch(task);
+            }
+        }
+        Ok(())
+    }
+
+    async fn append(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task: &Task,
+        event: &str,
+        data: Value,
+    ) -> anyhow::Result<()> {
+        let key = format!("{PROGRESS_PREFIX}{}", task.id);
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        pipe.cmd("XADD")
+            .arg(&key)
+            .arg("*")
+            .arg("event")
+            .arg(event)
+            .arg("data")
+            .arg(data.to_string())
+            .ignore();
+        if let Some(ttl) = self.ttl(task) {
+            pipe.expire(&key, ttl as i64).ignore();
+        }
+        let _: () = pipe.query_async(&mut *conn).await?;
+        Ok(())
+    }
+
+    pub async fn progress(
+        &self,
+        task_id: &str,
+        last_id: Option<String>,
+    ) -> anyhow::Result<BoxStream<'static, Progress>> {
+        if self
+            .get(&mut Tasks::connect().await?, task_id)
+            .await?
+            .is_none()
+        {
+            return Err(NotFound(format!("Task '{task_id}' not existed")).into());
+        }
+        // Blocking reads would pin a pooled connection for the whole stream.
+        let mut conn = Tasks::dedicated().await?;
+        let executor = self.clone();
+        let task_id = task_id.to_owned();
+        let stream = stream! {
+            let mut last_id = last_id.unwrap_or_else(|| "0".to_owned());
+            loop {
+                let entries = match executor.read_progress(&mut conn, &task_id, &last_id).await {
+                    Ok(entries) => entries,
+                    Err(err) => {
+                        yield Progress::error(&last_id, err);
+                        break;
                     }
+                };
+                if entries.is_empty() {
+                    let task = match Tasks::connect().await {
+                        Ok(mut conn) => executor.get(&mut conn, &task_id).await,
+                        Err(err) => Err(err),
+                    };
+                    match task {
+                        Ok(Some(task)) if !task.status.is_completed() => continue,
+                        Ok(Some(task)) => {
+                            yield Progress {
+                                id: last_id.clone(),
+                                event: "done".to_owned(),
+                                data: json!({ "status": task.status }).to_string(),
+                            };
+                        }
+                        Ok(None) => (),
+                        Err(err) => yield Progress::error(&last_id, err),
+                    }
+                    break;
                 }
-                if let Some(usage) = usage_encoded {
-                    encoder.write("\",\"usage\":".as_bytes()).await?;
-                    encoder
-                        .write(serde_json::to_string(&usage)?.as_bytes())
-                        .await?;
-                } else {
-                    encoder.write("\",\"usage\":null".as_bytes()).await?;
+                let mut done = false;
+                for progress in entries {
+                    last_id = progress.id.clone();
+                    done = progress.event == "done";
+                    yield progress;
+                }
+                if done {
+                    break;
                 }
-                encoder.write("}}".as_bytes()).await?;
-                encoder.shutdown().await?;
-                self.set_raw(conn, &task.id, encoder.into_inner()).await?;
             }
-            Err(err) => {
-                task.status = Status::Failed;
-                task.err_msg = Some(err.to_string());
-                self.set(conn, &task).await?;
+        };
+        Ok(Box::pin(stream))
+    }
+
+    async fn read_progress(
+        &self,
+        conn: &mut impl ConnectionLike,
+        task_id: &str,
+        last_id: &str,
+    ) -> anyhow::Result<Vec<Progress>> {
+        let reply: Option<Vec<redis::Value>> = redis::cmd("XREAD")
+            .arg("COUNT")
+            .arg(PROGRESS_BATCH_SIZE)
+            .arg("BLOCK")
+            .arg(PROGRESS_BLOCK.as_millis() as u64)
+            .arg("STREAMS")
+            .arg(format!("{PROGRESS_PREFIX}{task_id}"))
+            .arg(last_id)
+            .query_async(conn)
+            .await?;
+        let mut entries = Vec::new();
+        for stream in reply.unwrap_or_default() {
+            let stream: Vec<redis::Value> = redis::from_redis_value(&stream)?;
+            let Some(values) = stream.get(1) else {
+                continue;
+            };
+            for value in redis::from_redis_value::<Vec<redis::Value>>(values)? {
+                let (id, mut fields): (String, HashMap<String, String>) =
+                    redis::from_redis_value(&value)?;
+                entries.push(Progress {
+                    id,
+                    event: fields.remove("event").unwrap_or_default(),
+                    data: fields.remove("data").unwrap_or_default(),
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    fn is_transient(&self, err: &anyhow::Error) -> bool {
+        for cause in err.chain() {
+            if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+                return err.is_timeout()
+                    || err.is_connect()
+                    || err.is_request()
+                    || err
+                        .status()
+                        .is_some_and(|status| status.as_u16() == 429 || status.is_server_error());
+            }
+            if cause.is::<redis::RedisError>() || cause.is::<std::io::Error>() {
+                return true;
             }
         }
+        // Only a status code leading a message counts, not one quoted from a response body.
+        let pattern = TRANSIENT_PATTERN.get_or_init(|| {
+            Regex::new(r"(?i)^(http\s*)?(status(\s*code)?\s*:?\s*)?(429|5\d\d)\b").unwrap()
+        });
+        err.chain()
+            .any(|cause| pattern.is_match(&cause.to_string()))
+    }
+
+    fn backoff(&self, retries: u32) -> Duration {
+        let base = self
+            .config
+            .retry_backoff
+            .saturating_mul(1 << retries.saturating_sub(1).min(16))
+            .min(self.config.max_backoff)
+            .max(1)
+            * 1000;
+        let jitter = (Uuid::new_v4().as_u128() % (base as u128 / 2 + 1)) as u64;
+        Duration::from_millis(base / 2 + jitter)
+    }
+
+    async fn dead_letter(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task_id: &str,
+        reason: String,
+    ) -> anyhow::Result<()> {
+        let dead_letter = DeadLetter {
+            task_id: task_id.to_owned(),
+            reason,
+            dead_time: DateTime::local(),
+            task: None,
+        };
+        let mut pipe = redis::pipe();
+        pipe.hset(DEAD_LETTERS, task_id, serde_json::to_string(&dead_letter)?)
+            .ignore();
+        pipe.zadd(
+            DEAD_LETTER_QUEUE,
+            task_id,
+            dead_letter.dead_time.timestamp_millis(),
+        )
+        .ignore();
+        // Past the longest task lifetime the task is gone and the entry can never be requeued.
+        if let Some(lifetime) = self.lifetime() {
+            pipe.cmd("EVAL")
+                .arg(TRIM_DEAD_LETTERS_SCRIPT)
+                .arg(2)
+                .arg(DEAD_LETTER_QUEUE)
+                .arg(DEAD_LETTERS)
+                .arg(dead_letter.dead_time.timestamp_millis() - lifetime as i64 * 1000)
+                .arg(DEAD_LETTER_TRIM_SIZE)
+                .ignore();
+        }
+        let _: () = pipe.query_async(&mut *conn).await?;
         Ok(())
     }
 
-    pub async fn result(
+    pub async fn dead_letters(
+        &self,
+        conn: &mut MultiplexedConnection,
+        offset: usize,
+        limit: usize,
+    ) -> anyhow::Result<Vec<DeadLetter>> {
+        let limit = limit.clamp(1, MAX_LIST_LIMIT);
+        let end = offset
+            .checked_add(limit)
+            .and_then(|end| isize::try_from(end).ok())
+            .ok_or_else(|| BadRequest(format!("Invalid offset {offset}")))?;
+        let task_ids: Vec<String> = conn
+            .zrevrange(DEAD_LETTER_QUEUE, offset as isize, end - 1)
+            .await?;
+        if task_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let values: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(DEAD_LETTERS)
+            .arg(&task_ids)
+            .query_async(&mut *conn)
+            .await?;
+        let mut dead_letters = Vec::with_capacity(task_ids.len());
+        for (task_id, value) in task_ids.iter().zip(values) {
+            let Some(value) = value else {
+                continue;
+            };
+            let mut dead_letter: DeadLetter = serde_json::from_str(&value)?;
+            dead_letter.task = match self.get(conn, task_id).await {
+                Ok(task) => task.map(TaskSummary::from),
+                Err(_) => None,
+            };
+            dead_letters.push(dead_letter);
+        }
+        Ok(dead_letters)
+    }
+
+    pub async fn requeue_dead_letter(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task_id: &str,
+    ) -> anyhow::Result<Task> {
+        let existed: bool = conn.hexists(DEAD_LETTERS, task_id).await?;
+        if !existed {
+            return Err(anyhow!("Dead letter '{task_id}' not existed"));
+        }
+        let mut task = self
+            .get(conn, task_id)
+            .await
+            .ok()
+            .flatten()
+            .ok_or_else(|| BadRequest(format!("Task '{task_id}' cannot be requeued")))?;
+        let removed: i64 = conn.zrem(DEAD_LETTER_QUEUE, task_id).await?;
+        if removed == 0 {
+            return Err(anyhow!("Dead letter '{task_id}' not existed"));
+        }
+        let _: () = conn.hdel(DEAD_LETTERS, task_id).await?;
+        let _: () = conn.del(format!("{PROGRESS_PREFIX}{task_id}")).await?;
+        task.retry();
+        self.enqueue(conn, &task).await?;
+        Ok(task)
+    }
+
+    pub async fn requeue_dead_letters(
+        &self,
+        conn: &mut MultiplexedConnection,
+    ) -> anyhow::Result<usize> {
+        let task_ids: Vec<String> = conn.zrange(DEAD_LETTER_QUEUE, 0, -1).await?;
+        let mut requeued = 0;
+        for task_id in task_ids {
+            match self.requeue_dead_letter(conn, &task_id).await {
+                Ok(_) => requeued += 1,
+                Err(err) => eprintln!("Failed to requeue dead letter '{task_id}': {:?}", err),
+            }
+        }
+        Ok(requeued)
+    }
+
+    pub async fn purge_dead_letters(
+        &self,
+        conn: &mut MultiplexedConnection,
+    ) -> anyhow::Result<usize> {
+        let task_ids: Vec<String> = conn.zrange(DEAD_LETTER_QUEUE, 0, -1).await?;
+        for chunk in task_ids.chunks(BATCH_CHUNK_SIZE) {
+            let mut pipe = redis::pipe();
+            pipe.zrem(DEAD_LETTER_QUEUE, chunk).ignore();
+            pipe.hdel(DEAD_LETTERS, chunk).ignore();
+            pipe.del(chunk).ignore();
+            let _: () = pipe.query_async(&mut *conn).await?;
+            self.unindex(conn, chunk).await?;
+        }
+        Ok(task_ids.len())
+    }
+
+    pub async fn cancel(
         &self,
-        mut conn: Connection<Tasks>,
-        task_id: &String,
-        timeout: u64,
+        conn: &mut MultiplexedConnection,
+        task_id: &str,
     ) -> anyhow::Result<Task> {
-        let now = Instant::now();
-        let mut interval = time::interval(Duration::from_secs(1));
-        interval.tick().await;
-        loop {
-            if let Some(task) = self.get(&mut conn, task_id).await? {
-                if task.status == Status::Finished || task.status == Status::Failed {
+        let mut task = self
+            .get(conn, task_id)
+            .await?
+            .ok_or_else(|| NotFound(format!("Task '{task_id}' not existed")))?;
+        match task.status {
+            Status::Scheduled => {
+                let removed: i64 = conn.zrem(SCHEDULED_QUEUE, task_id).await?;
+                if removed > 0 {
+                    self.set_cancelled(conn, &mut task).await?;
                     return Ok(task);
                 }
-                if timeout > 0 && now.elapsed().as_secs() >= timeout {
+            }
+            Status::Pending => {
+                let removed: i64 = conn.lrem(self.queue(&task.priority), 0, task_id).await?;
+                if removed > 0 {
+                    self.set_cancelled(conn, &mut task).await?;
                     return Ok(task);
                 }
-                interval.tick().await;
+            }
+            Status::Running => (),
+            _ => {
+                return Err(BadRequest(format!("Task '{task_id}' has already completed")).into());
+            }
+        }
+        let key = format!("{CANCEL_PREFIX}{task_id}");
+        let _: () = match self.ttl(&task) {
+            Some(ttl) => conn.set_ex(&key, 1, ttl).await?,
+            None => conn.set(&key, 1).await?,
+        };
+        Ok(task)
+    }
+
+    async fn is_cancelled(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task_id: &str,
+    ) -> anyhow::Result<bool> {
+        Ok(conn.exists(format!("{CANCEL_PREFIX}{task_id}")).await?)
+    }
+
+    async fn set_cancelled(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task: &mut Task,
+    ) -> anyhow::Result<()> {
+        task.status = Status::Cancelled;
+        task.finish_time = Some(DateTime::local());
+        self.set(conn, task).await?;
+        let _: () = conn.del(format!("{CANCEL_PREFIX}{}", task.id)).await?;
+        self.complete(conn, task).await
+    }
+
+    pub async fn result(&self, task_id: &str, timeout: u64) -> anyhow::Result<Task> {
+        // Watch before reading so a completion in between is not missed.
+        let mut waiter = Waiter::new(task_id);
+        let deadline = time::Instant::now() + Duration::from_secs(timeout);
+        loop {
+            // The pooled connection is returned while waiting.
+            let task = self
+                .get(&mut Tasks::connect().await?, task_id)
+                .await?
+                .ok_or_else(|| NotFound(format!("Task '{task_id}' not existed")))?;
+            if task.status.is_completed() || (timeout > 0 && time::Instant::now() >= deadline) {
+                return Ok(task);
+            }
+            if timeout > 0 {
+                let _ = time::timeout_at(deadline, waiter.wait()).await;
+            } else {
+                waiter.wait().await;
+            }
+        }
+    }
+
+    fn listen() {
+        LISTENER.call_once(|| {
+            tokio::spawn(async {
+                loop {
+                    if let Err(err) = Self::relay().await {
+                        eprintln!("Failed to listen for completions: {:?}", err);
+                    }
+                    time::sleep(POLL_INTERVAL).await;
+                }
+            });
+        });
+    }
+
+    async fn relay() -> anyhow::Result<()> {
+        let mut pubsub = Tasks::psubscribe(&format!("{COMPLETION_PREFIX}*")).await?;
+        // Completions published while unsubscribed are lost, so every waiter checks again.
+        Waiter::notify(None);
+        let mut messages = pin!(pubsub.on_message());
+        while let Some(message) = messages.next().await {
+            if let Some(task_id) = message.get_channel_name().strip_prefix(COMPLETION_PREFIX) {
+                Waiter::notify(Some(task_id));
+            }
+        }
+        Err(anyhow!("Completion subscription closed"))
+    }
+
+    pub async fn batch(
+        &self,
+        conn: &mut MultiplexedConnection,
+        batch_id: &str,
+        with_tasks: bool,
+    ) -> anyhow::Result<BatchStatus> {
+        let task_ids = self.batch_task_ids(conn, batch_id).await?;
+        let mut counts = HashMap::new();
+        for status in self.statuses(conn, &task_ids).await?.into_iter().flatten() {
+            *counts.entry(status).or_insert(0) += 1;
+        }
+        let tasks = if with_tasks {
+            let found = self.get_many(conn, &task_ids).await?;
+            let mut tasks = Vec::with_capacity(found.len());
+            for (task_id, task) in task_ids.iter().zip(found) {
+                match task {
+                    Ok(task) => tasks.extend(task),
+                    Err(err) => eprintln!("Failed to read task '{task_id}': {:?}", err),
+                }
+            }
+            Some(tasks)
+        } else {
+            None
+        };
+        Ok(BatchStatus {
+            id: batch_id.to_owned(),
+            total: task_ids.len(),
+            counts,
+            tasks,
+        })
+    }
+
+    pub async fn batch_results(
+        &self,
+        conn: &mut MultiplexedConnection,
+        batch_id: &str,
+    ) -> anyhow::Result<BoxStream<'static, Vec<u8>>> {
+        let task_ids = self.batch_task_ids(conn, batch_id).await?;
+        let pending = self
+            .statuses(conn, &task_ids)
+            .await?
+            .iter()
+            .filter(|status| !status.as_ref().is_some_and(|status| status.is_completed()))
+            .count();
+        if pending > 0 {
+            return Err(
+                BadRequest(format!("Batch '{batch_id}' has {pending} unfinished tasks")).into(),
+            );
+        }
+        let executor = self.clone();
+        let batch_id = batch_id.to_owned();
+        let stream = stream! {
+            for chunk in task_ids.chunks(BATCH_CHUNK_SIZE) {
+                let tasks = match Tasks::connect().await {
+                    Ok(mut conn) => executor.get_many(&mut conn, chunk).await,
+                    Err(err) => Err(err),
+                };
+                let tasks = match tasks {
+                    Ok(tasks) => tasks,
+                    Err(err) => {
+                        eprintln!("Failed to read results of batch '{batch_id}': {:?}", err);
+                        break;
+                    }
+                };
+                let mut lines = Vec::new();
+                for (task_id, task) in chunk.iter().zip(tasks) {
+                    let line = match task {
+                        Ok(Some(task)) => serde_json::to_vec(&task),
+                        Ok(None) => continue,
+                        Err(err) => {
+                            eprintln!("Failed to read task '{task_id}': {:?}", err);
+                            serde_json::to_vec(&json!({
+                                "id": task_id,
+                                "status": Status::Failed,
+                                "err_msg": format!("Failed to read task: {:#}", err),
+                            }))
+                        }
+                    };
+                    match line {
+                        Ok(line) => {
+                            lines.extend(line);
+                            lines.push(b'\n');
+                        }
+                        Err(err) => eprintln!("Failed to encode task '{task_id}': {:?}", err),
+                    }
+                }
+                yield lines;
+            }
+        };
+        Ok(Box::pin(stream))
+    }
+
+    async fn statuses(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task_ids: &[String],
+    ) -> anyhow::Result<Vec<Option<Status>>> {
+        // Read from the status indexes so counting never decodes full tasks.
+        let mut statuses = Vec::with_capacity(task_ids.len());
+        for chunk in task_ids.chunks(BATCH_CHUNK_SIZE) {
+            let mut pipe = redis::pipe();
+            for status in Status::all() {
+                pipe.cmd("ZMSCORE")
+                    .arg(format!("{INDEX_PREFIX}{}", status.name()))
+                    .arg(chunk);
+            }
+            let scores: Vec<Vec<Option<f64>>> = pipe.query_async(&mut *conn).await?;
+            let mut found = vec![None; chunk.len()];
+            for (status, scores) in Status::all().into_iter().zip(scores) {
+                for (found, score) in found.iter_mut().zip(scores) {
+                    if score.is_some() {
+                        *found = Some(status.clone());
+                    }
+                }
+            }
+            statuses.extend(found);
+        }
+        Ok(statuses)
+    }
+
+    async fn batch_task_ids(
+        &self,
+        conn: &mut MultiplexedConnection,
+        batch_id: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let task_ids: Vec<String> = conn
+            .lrange(format!("{BATCH_PREFIX}{batch_id}"), 0, -1)
+            .await?;
+        if task_ids.is_empty() {
+            return Err(anyhow!("Batch '{batch_id}' not existed"));
+        }
+        Ok(task_ids)
+    }
+
+    async fn get_many(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task_ids: &[String],
+    ) -> anyhow::Result<Vec<anyhow::Result<Option<Task>>>> {
+        let mut tasks = Vec::with_capacity(task_ids.len());
+        for chunk in task_ids.chunks(BATCH_CHUNK_SIZE) {
+            let values: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+                .arg(chunk)
+                .query_async(&mut *conn)
+                .await?;
+            // A single unreadable payload must not fail the whole read.
+            for value in values {
+                match value {
+                    Some(value) => tasks.push(self.decode(&value).await.map(Some)),
+                    None => tasks.push(Ok(None)),
+                }
+            }
+        }
+        Ok(tasks)
+    }
+
+    pub async fn list(
+        &self,
+        conn: &mut MultiplexedConnection,
+        filter: &TaskFilter,
+    ) -> anyhow::Result<TaskPage> {
+        let limit = filter
+            .limit
+            .unwrap_or(DEFAULT_LIST_LIMIT)
+            .clamp(1, MAX_LIST_LIMIT);
+        let key = match &filter.status {
+            Some(status) => format!("{INDEX_PREFIX}{}", status.name()),
+            None => INDEX_ALL.to_owned(),
+        };
+        let millis = |secs: i64, name: &str| {
+            secs.checked_mul(1000)
+                .ok_or_else(|| BadRequest(format!("Invalid '{name}' timestamp {secs}")))
+        };
+        let min = match filter.since {
+            Some(since) => millis(since, "since")?.to_string(),
+            None => "-inf".to_owned(),
+        };
+        let (max, after) = match &filter.cursor {
+            Some(cursor) => {
+                let (score, task_id) = cursor
+                    .split_once(':')
+                    .and_then(|(score, task_id)| Some((score.parse::<i64>().ok()?, task_id)))
+                    .ok_or_else(|| BadRequest(format!("Invalid cursor '{cursor}'")))?;
+                (score.to_string(), Some((score, task_id.to_owned())))
+            }
+            None => match filter.until {
+                Some(until) => (millis(until, "until")?.to_string(), None),
+                None => ("+inf".to_owned(), None),
+            },
+        };
+        let mut tasks = Vec::new();
+        let mut expired = Vec::new();
+        let mut next_cursor = None;
+        let mut offset = 0;
+        for _ in 0..MAX_LIST_ROUNDS {
+            let entries: Vec<(String, f64)> = redis::cmd("ZREVRANGEBYSCORE")
+                .arg(&key)
+                .arg(&max)
+                .arg(&min)
+                .arg("WITHSCORES")
+                .arg("LIMIT")
+                .arg(offset)
+                .arg(LIST_SCAN_SIZE)
+                .query_async(&mut *conn)
+                .await?;
+            if entries.is_empty() {
+                next_cursor = None;
+                break;
+            }
+            offset += entries.len();
+            let exhausted = entries.len() < LIST_SCAN_SIZE;
+            // Members sharing a score come back in reverse lexical order, so the
+            // cursor excludes every id already seen at the cursor's score.
+            let entries = entries
+                .into_iter()
+                .map(|(task_id, score)| (task_id, score as i64))
+                .filter(|(task_id, score)| match &after {
+                    Some((after_score, after_id)) => {
+                        score < after_score || (score == after_score && task_id < after_id)
+                    }
+                    None => true,
+                })
+                .collect::<Vec<_>>();
+            let task_ids = entries
+                .iter()
+                .map(|(task_id, _)| task_id.clone())
+                .collect::<Vec<_>>();
+            let found = self.get_many(conn, &task_ids).await?;
+            for ((task_id, score), task) in entries.into_iter().zip(found) {
+                next_cursor = Some(format!("{score}:{task_id}"));
+                let task = match task {
+                    Ok(Some(task)) => task,
+                    Ok(None) => {
+                        expired.push(task_id);
+                        continue;
+                    }
+                    Err(err) => {
+                        eprintln!("Failed to read task '{task_id}': {:?}", err);
+                        continue;
+                    }
+                };
+                if filter.status.is_some() && Some(&task.status) != filter.status.as_ref() {
+                    continue;
+                }
+                if filter.model.is_some() && task.model != filter.model {
+                    continue;
+                }
+                if filter.submitter.is_some() && task.submitter != filter.submitter {
+                    continue;
+                }
+                tasks.push(task.into());
+                if tasks.len() == limit {
+                    break;
+                }
+            }
+            if tasks.len() == limit {
+                break;
+            }
+            if exhausted {
+                next_cursor = None;
+                break;
+            }
+        }
+        self.unindex(conn, &expired).await?;
+        Ok(TaskPage { tasks, next_cursor })
+    }
+
+    async fn index(&self, conn: &mut MultiplexedConnection, task: &Task) -> anyhow::Result<()> {
+        let mut pipe = redis::pipe();
+        self.stage_index(&mut pipe, task);
+        let _: () = pipe.query_async(&mut *conn).await?;
+        Ok(())
+    }
+
+    fn stage_index(&self, pipe: &mut redis::Pipeline, task: &Task) {
+        let score = task.create_time.timestamp_millis();
+        pipe.zadd(INDEX_ALL, &task.id, score).ignore();
+        let mut keys = vec![INDEX_EXPIRY.to_owned(), INDEX_ALL.to_owned()];
+        for status in Status::all() {
+            let key = format!("{INDEX_PREFIX}{}", status.name());
+            if status == task.status {
+                pipe.zadd(&key, &task.id, score).ignore();
             } else {
-                return Err(anyhow!("Task '{task_id}' not existed"));
+                pipe.zrem(&key, &task.id).ignore();
             }
+            keys.push(key);
+        }
+        // Entries are trimmed when their task key expires, which for scheduled
+        // tasks is later than the create time suggests.
+        match self.expire_at(task) {
+            Some(expire_at) => pipe.zadd(INDEX_EXPIRY, &task.id, expire_at),
+            None => pipe.zrem(INDEX_EXPIRY, &task.id),
+        }
+        .ignore();
+        pipe.cmd("EVAL")
+            .arg(TRIM_INDEX_SCRIPT)
+            .arg(keys.len())
+            .arg(keys)
+            .arg(DateTime::local().timestamp_millis())
+            .arg(INDEX_TRIM_SIZE)
+            .ignore();
+    }
+
+    fn expire_at(&self, task: &Task) -> Option<i64> {
+        let ttl = self.ttl(task)?;
+        Some(DateTime::local().timestamp_millis() + ttl as i64 * 1000)
+    }
+
+    async fn unindex(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task_ids: &[String],
+    ) -> anyhow::Result<()> {
+        if task_ids.is_empty() {
+            return Ok(());
+        }
+        let mut pipe = redis::pipe();
+        pipe.zrem(INDEX_ALL, task_ids).ignore();
+        pipe.zrem(INDEX_EXPIRY, task_ids).ignore();
+        for status in Status::all() {
+            pipe.zrem(format!("{INDEX_PREFIX}{}", status.name()), task_ids)
+                .ignore();
         }
+        let _: () = pipe.query_async(&mut *conn).await?;
+        Ok(())
     }
 
     pub async fn get(
         &self,
-        conn: &mut Connection<Tasks>,
+        conn: &mut MultiplexedConnection,
         task_id: &str,
     ) -> anyhow::Result<Option<Task>> {
         if let Some(value) = conn.get::<&str, Option<Vec<u8>>>(task_id).await? {
-            let decompressed = self.decompress(&value).await?;
-            let task = serde_json::from_slice(&decompressed)?;
-            Ok(Some(task))
+            Ok(Some(self.decode(&value).await?))
         } else {
             Ok(None)
         }
     }
 
-    async fn set(&self, conn: &mut Connection<Tasks>, task: &Task) -> anyhow::Result<()> {
+    async fn decode(&self, value: &[u8]) -> anyhow::Result<Task> {
+        let decompressed = self.decompress(value).await?;
+        Ok(serde_json::from_slice(&decompressed)?)
+    }
+
+    pub async fn query(
+        &self,
+        conn: &mut MultiplexedConnection,
+        task_id: &str,
+    ) -> anyhow::Result<Option<Task>> {
+        if let Some(task) = self.get(conn, task_id).await? {
+            return Ok(Some(task));
+        }
+        // Only ids the executor could have issued are looked up, any other is simply unknown.
+        let issued = Uuid::parse_str(task_id).is_ok_and(|id| id.to_string() == task_id);
+        if !self.config.retention.archive || !issued {
+            return Ok(None);
+        }
+        let storage = Service::<Storage>::inject();
+        let name = format!("{ARCHIVE_PREFIX}task-{task_id}.json.zst");
+        let (stream, _) = match storage.get(&name).await {
+            Ok(object) => object,
+            Err(err) if err.downcast_ref::<NotFound>().is_some() => return Ok(None),
+            Err(err) => {
+                eprintln!("Failed to read archive of task '{task_id}': {:?}", err);
+                return Ok(None);
+            }
+        };
+        let value = stream.try_collect::<Vec<_>>().await?.concat();
+        Ok(Some(self.decode(&value).await?))
+    }
+
+    async fn archive(&self, task_id: &str) -> anyhow::Result<()> {
+        let mut conn = Tasks::connect().await?;
+        let value: Option<Vec<u8>> = conn.get(task_id).await?;
+        let value = value.ok_or_else(|| NotFound(format!("Task '{task_id}' not existed")))?;
+        let meta = ObjectMeta {
+            content_type: "application/zstd".to_owned(),
+            content_length: value.len() as u64,
+            ..Default::default()
+        };
+        Service::<Storage>::inject()
+            .put(
+                &format!("{ARCHIVE_PREFIX}task-{task_id}.json.zst"),
+                Box::pin(Cursor::new(value)),
+                meta,
+            )
+            .await
+    }
+
+    fn ttl(&self, task: &Task) -> Option<u64> {
+        let retention = &self.config.retention;
+        let ttl = match task.status {
+            Status::Scheduled | Status::Pending => retention.pending,
+            Status::Running => retention.running,
+            Status::Finished => retention.finished,
+            Status::Failed => retention.failed,
+            Status::Cancelled => retention.cancelled,
+        }
+        .unwrap_or(self.config.expiration);
+        if ttl == 0 {
+            return None;
+        }
+        Some(ttl + self.delay(task))
+    }
+
+    pub fn retention(&self, task: &Task) -> Option<u64> {
+        Some(self.lifetime()? + self.delay(task))
+    }
+
+    fn delay(&self, task: &Task) -> u64 {
+        // Scheduled tasks must outlive the wait until their run time.
+        match (&task.status, &task.run_at) {
+            (Status::Scheduled, Some(run_at)) => {
+                (run_at.timestamp() - DateTime::local().timestamp()).max(0) as u64
+            }
+            _ => 0,
+        }
+    }
+
+    fn lifetime(&self) -> Option<u64> {
+        let retention = &self.config.retention;
+        let ttl =
+            |ttl: Option<u64>| Some(ttl.unwrap_or(self.config.expiration)).filter(|ttl| *ttl > 0);
+        let completed = [retention.finished, retention.failed, retention.cancelled]
+            .into_iter()
+            .map(ttl)
+            .collect::<Option<Vec<_>>>()?;
+        Some(ttl(retention.pending)? + ttl(retention.running)? + completed.into_iter().max()?)
+    }
+
+    async fn set(&self, conn: &mut MultiplexedConnection, task: &Task) -> anyhow::Result<()> {
         let value = self.compress(serde_json::to_string(task)?).await?;
-        let _: () = conn.set_ex(&task.id, value, self.config.expiration).await?;
-        Ok(())
+        self.set_raw(conn, task, value).await?;
+        self.index(conn, task).await
     }
 
-    async fn set_raw<K: AsRef<str>>(
+    async fn set_raw(
         &self,
-        conn: &mut Connection<Tasks>,
-        key: K,
+        conn: &mut MultiplexedConnection,
+        task: &Task,
         value: Vec<u8>,
     ) -> anyhow::Result<()> {
-        let _: () = conn
-            .set_ex(key.as_ref(), value, self.config.expiration)
-            .await?;
+        let mut pipe = redis::pipe();
+        self.stage_raw(&mut pipe, task, value);
+        let _: () = pipe.query_async(&mut *conn).await?;
         Ok(())
     }
 
+    fn stage_raw(&self, pipe: &mut redis::Pipeline, task: &Task, value: Vec<u8>) {
+        match self.ttl(task) {
+            Some(ttl) => pipe.set_ex(&task.id, value, ttl),
+            None => pipe.set(&task.id, value),
+        }
+        .ignore();
+    }
+
     async fn compress<T: AsRef<str>>(&self, data: T) -> anyhow::Result<Vec<u8>> {
         let mut encoder = ZstdEncoder::new(Vec::new());
         encoder.write(data.as_ref().as_bytes()).await?;
@@ -209,7 +1667,170 @@ impl Executor {
         Ok(decoder.into_inner())
     }
 
-    fn escape(&self, s: &str) -> String {
-        s.replace("\n", "\\n").replace("\"", "\\\"")
+    fn escape(&self, s: &str) -> anyhow::Result<String> {
+        let quoted = serde_json::to_string(s)?;
+        Ok(quoted[1..quoted.len() - 1].to_owned())
+    }
+}
+
+struct Waiter {
+    task_id: String,
+    receiver: Option<broadcast::Receiver<()>>,
+}
+
+impl Waiter {
+    fn new(task_id: &str) -> Self {
+        Executor::listen();
+        let receiver = WAITERS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap()
+            .entry(task_id.to_owned())
+            .or_insert_with(|| broadcast::channel(1).0)
+            .subscribe();
+        Self {
+            task_id: task_id.to_owned(),
+            receiver: Some(receiver),
+        }
+    }
+
+    async fn wait(&mut self) {
+        if let Some(receiver) = &mut self.receiver {
+            let _ = receiver.recv().await;
+        }
+    }
+
+    fn notify(task_id: Option<&str>) {
+        let waiters = WAITERS.get_or_init(Default::default).lock().unwrap();
+        for (id, sender) in waiters.iter() {
+            if task_id.is_none() || task_id == Some(id.as_str()) {
+                let _ = sender.send(());
+            }
+        }
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        drop(self.receiver.take());
+        let mut waiters = WAITERS.get_or_init(Default::default).lock().unwrap();
+        if waiters
+            .get(&self.task_id)
+            .is_some_and(|sender| sender.receiver_count() == 0)
+        {
+            waiters.remove(&self.task_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::config::RetentionConfig;
+    use crate::entities::message::Message;
+    use crate::entities::request::ChatRequest;
+    use chrono::{Local, TimeDelta};
+
+    fn build_executor() -> Executor {
+        Executor {
+            config: Arc::new(ExecutorConfig {
+                num_workers: 1,
+                timeout: 30,
+                expiration: 86400,
+                heartbeat: 60,
+                max_requeues: 3,
+                max_retries: 3,
+                retry_backoff: 2,
+                max_backoff: 10,
+                starvation_interval: 10,
+                grace_period: 30,
+                retention: RetentionConfig::default(),
+            }),
+        }
+    }
+
+    fn build_task() -> Task {
+        Task::create(ChatRequest {
+            message: Message {
+                role: None,
+                text: Some("你是谁".to_owned()),
+                images: None,
+                videos: None,
+                context: None,
+            },
+            model: None,
+            options: None,
+        })
+    }
+
+    #[test]
+    fn test_expire_at_scheduled() {
+        let executor = build_executor();
+        let lifetime = executor.lifetime().unwrap() as i64;
+        let mut task = build_task();
+        let run_at = Local::now() + TimeDelta::seconds(lifetime * 2);
+        task.schedule(DateTime::from(run_at));
+        assert_eq!(task.status, Status::Scheduled);
+        let now = Local::now().timestamp_millis();
+        let expire_at = executor.expire_at(&task).unwrap();
+        // The index entry outlives both the lifetime and the run time.
+        assert!(expire_at > now + lifetime * 1000);
+        assert!(expire_at > run_at.timestamp_millis());
+        task.status = Status::Pending;
+        let expire_at = executor.expire_at(&task).unwrap();
+        assert!(expire_at > Local::now().timestamp_millis());
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let executor = build_executor();
+        for (message, transient) in [
+            ("503 Service Unavailable", true),
+            ("HTTP 502: Bad Gateway", true),
+            ("status code: 429", true),
+            ("Status: 500", true),
+            ("400 Bad Request", false),
+            ("Invalid model output of 500 tokens", false),
+            ("Request failed: {\"code\": 503}", false),
+        ] {
+            assert_eq!(
+                executor.is_transient(&anyhow!(message)),
+                transient,
+                "{message}"
+            );
+        }
+        let err = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        assert!(executor.is_transient(&err.into()));
+        let err = redis::RedisError::from((redis::ErrorKind::IoError, "Connection closed"));
+        assert!(executor.is_transient(&err.into()));
+    }
+
+    #[tokio::test]
+    async fn test_is_transient_reqwest() {
+        let executor = build_executor();
+        let err = reqwest::get("http://127.0.0.1:1").await.unwrap_err();
+        assert!(executor.is_transient(&err.into()));
+        let err = reqwest::get("invalid url").await.unwrap_err();
+        assert!(!executor.is_transient(&err.into()));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let executor = build_executor();
+        let backoff = executor.backoff(1);
+        assert!(backoff >= Duration::from_secs(1) && backoff <= Duration::from_secs(2));
+        for retries in [4, 16, 64, u32::MAX] {
+            let backoff = executor.backoff(retries);
+            assert!(backoff >= Duration::from_secs(5) && backoff <= Duration::from_secs(10));
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        let executor = build_executor();
+        let raw = "tab\tcr\rlf\nquote\"backslash\\bell\u{7}中文";
+        let escaped = executor.escape(raw).unwrap();
+        let parsed: String = serde_json::from_str(&format!("\"{escaped}\"")).unwrap();
+        assert_eq!(parsed, raw);
     }
 }
@@ -1,6 +1,8 @@
+pub mod cron;
 pub mod executor;
 pub mod models;
 pub mod oss;
+pub mod webhook;
 
 use std::ops::Deref;
 
@@ -9,7 +11,7 @@ use ref_cast::RefCast;
 use rocket::{
     http::Status,
     request::{FromRequest, Outcome},
-    Request, State,
+    Phase, Request, Rocket, State,
 };
 use state::{InitCell, TypeMap};
 
@@ -38,6 +40,16 @@ impl<T: Inject> Service<T> {
     }
 }
 
+fn configure<P: Phase>(rocket: &Rocket<P>) -> anyhow::Result<()> {
+    if SERVICE_CONFIG.try_get().is_none() {
+        let config = rocket
+            .state::<Config>()
+            .ok_or_else(|| anyhow!("State 'Config' not existed"))?;
+        SERVICE_CONFIG.set(config.services.clone());
+    }
+    Ok(())
+}
+
 impl<T: Inject> Deref for Service<T> {
     type Target = T;
 
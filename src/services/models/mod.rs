@@ -1,55 +1,163 @@
-pub mod qwen3;
-pub mod qwen3vl;
+pub mod registry;
 
-pub use qwen3::Qwen3;
-pub use qwen3vl::Qwen3VL;
+pub use registry::ModelRegistry;
 
-use agentx::{Completion, ModelOptions, OpenAIModelOptions, Prompt, Stream, StreamingChatModel};
-use anyhow::anyhow;
+use agentx::{
+    Completion, Model as _, ModelOptions, OpenAIModelOptions, Prompt, Stream, StreamingChatModel,
+};
 
-use crate::{
-    entities::config::{ModelConfig, ServiceConfig},
-    services::{Inject, Service},
+use crate::entities::{
+    config::{ModelConfig, ModelLimits},
+    request::GenerationOptions,
 };
 
-pub trait Model: StreamingChatModel + Inject {
-    fn new(options: ModelOptions) -> Self;
+struct OpenAIModel {
+    options: ModelOptions,
+}
+
+impl agentx::Model for OpenAIModel {
+    fn options(&self) -> &ModelOptions {
+        &self.options
+    }
+}
+
+impl StreamingChatModel for OpenAIModel {}
 
-    fn name() -> &'static str;
+pub struct Model {
+    name: String,
+    inner: OpenAIModel,
+    defaults: GenerationOptions,
+    limits: ModelLimits,
 }
 
-impl<T: Model> Inject for T {
-    fn new(config: &ServiceConfig) -> Self {
-        let name = Self::name();
+impl Model {
+    pub fn new(name: &str, config: &ModelConfig) -> Self {
         let ModelConfig {
             model,
             base_url,
             api_key,
-        } = &config
-            .models
-            .get(name)
-            .ok_or_else(|| anyhow!("missing model configuration '{}'", name))
-            .unwrap();
-        <Self as Model>::new(
-            OpenAIModelOptions::new()
-                .model(model)
-                .base_url(base_url)
-                .api_key(api_key)
-                .into(),
-        )
+            defaults,
+            limits,
+        } = config;
+        Self {
+            name: name.to_owned(),
+            inner: OpenAIModel {
+                options: OpenAIModelOptions::new()
+                    .model(model)
+                    .base_url(base_url)
+                    .api_key(api_key)
+                    .into(),
+            },
+            defaults: defaults.clone(),
+            limits: limits.clone(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn resolve(&self, options: Option<GenerationOptions>) -> GenerationOptions {
+        let mut options = options.unwrap_or_default().or(&self.defaults);
+        options.max_tokens = match (options.max_tokens, self.limits.max_tokens) {
+            (Some(max_tokens), Some(limit)) => Some(max_tokens.min(limit)),
+            (max_tokens, limit) => max_tokens.or(limit),
+        };
+        options
+    }
+
+    pub async fn completion(
+        &self,
+        promt: &Prompt,
+        options: &GenerationOptions,
+    ) -> anyhow::Result<Completion> {
+        self.inner.completion(promt, options.clone().into()).await
+    }
+
+    pub async fn stream(
+        &self,
+        promt: &Prompt,
+        options: &GenerationOptions,
+    ) -> anyhow::Result<Stream<Completion>> {
+        self.inner.stream(promt, options.clone().into()).await
+    }
+
+    pub async fn text_stream(
+        &self,
+        promt: &Prompt,
+        options: &GenerationOptions,
+    ) -> anyhow::Result<Stream<String>> {
+        self.inner.text_stream(promt, options.clone().into()).await
     }
 }
 
-impl<M: Model> Service<M> {
-    pub async fn completion(&self, promt: &Prompt) -> anyhow::Result<Completion> {
-        self.0.completion(promt, ModelOptions::default()).await
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_model(defaults: GenerationOptions, max_tokens: Option<u32>) -> Model {
+        Model::new(
+            "qwen3",
+            &ModelConfig {
+                model: "qwen-turbo-latest".to_owned(),
+                base_url: "http://127.0.0.1".to_owned(),
+                api_key: String::new(),
+                defaults,
+                limits: ModelLimits { max_tokens },
+            },
+        )
     }
 
-    pub async fn stream(&self, promt: &Prompt) -> anyhow::Result<Stream<Completion>> {
-        self.0.stream(promt, ModelOptions::default()).await
+    #[test]
+    fn test_resolve_defaults() {
+        let model = build_model(
+            GenerationOptions {
+                temperature: Some(0.7),
+                top_p: Some(0.9),
+                stop: Some(vec!["\n".to_owned()]),
+                ..Default::default()
+            },
+            None,
+        );
+        let options = model.resolve(None);
+        assert_eq!(options.temperature, Some(0.7));
+        assert_eq!(options.top_p, Some(0.9));
+        assert_eq!(options.stop, Some(vec!["\n".to_owned()]));
+        let options = model.resolve(Some(GenerationOptions {
+            temperature: Some(0.1),
+            seed: Some(42),
+            ..Default::default()
+        }));
+        assert_eq!(options.temperature, Some(0.1));
+        assert_eq!(options.top_p, Some(0.9));
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.max_tokens, None);
     }
 
-    pub async fn text_stream(&self, promt: &Prompt) -> anyhow::Result<Stream<String>> {
-        self.0.text_stream(promt, ModelOptions::default()).await
+    #[test]
+    fn test_resolve_max_tokens() {
+        let defaults = GenerationOptions {
+            max_tokens: Some(1024),
+            ..Default::default()
+        };
+        let model = build_model(defaults, Some(4096));
+        assert_eq!(model.resolve(None).max_tokens, Some(1024));
+        for (max_tokens, resolved) in [(100, 100), (4096, 4096), (100000, 4096)] {
+            let options = model.resolve(Some(GenerationOptions {
+                max_tokens: Some(max_tokens),
+                ..Default::default()
+            }));
+            assert_eq!(options.max_tokens, Some(resolved));
+        }
+        let model = build_model(GenerationOptions::default(), Some(4096));
+        assert_eq!(model.resolve(None).max_tokens, Some(4096));
+        let model = build_model(
+            GenerationOptions {
+                max_tokens: Some(8192),
+                ..Default::default()
+            },
+            Some(4096),
+        );
+        assert_eq!(model.resolve(None).max_tokens, Some(4096));
     }
 }
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use agentx::Prompt;
+use anyhow::anyhow;
+use rocket::fairing::AdHoc;
+
+use crate::{
+    entities::{
+        config::{RoutingConfig, ServiceConfig},
+        response::BadRequest,
+    },
+    services::{configure, models::Model, Inject, Service, SERVICE_CONFIG},
+};
+
+pub struct ModelRegistry {
+    models: HashMap<String, Model>,
+    routing: RoutingConfig,
+}
+
+impl Inject for ModelRegistry {
+    fn new(config: &ServiceConfig) -> Self {
+        let models = config
+            .models
+            .iter()
+            .map(|(name, config)| (name.to_owned(), Model::new(name, config)))
+            .collect::<HashMap<_, _>>();
+        if let Err(err) = Self::validate(config) {
+            panic!("{:#}", err);
+        }
+        Self {
+            models,
+            routing: config.routing.clone(),
+        }
+    }
+}
+
+impl ModelRegistry {
+    pub fn register() -> AdHoc {
+        AdHoc::try_on_ignite("Model Registry", |rocket| async {
+            let result = configure(&rocket).and_then(|()| Self::validate(SERVICE_CONFIG.get()));
+            match result {
+                Ok(()) => {
+                    Service::<Self>::inject();
+                    Ok(rocket)
+                }
+                Err(err) => {
+                    eprintln!("Invalid model configuration: {:#}", err);
+                    Err(rocket)
+                }
+            }
+        })
+    }
+
+    fn validate(config: &ServiceConfig) -> anyhow::Result<()> {
+        for name in [&config.routing.text, &config.routing.media] {
+            if !config.models.contains_key(name) {
+                return Err(anyhow!("Missing model configuration '{}'", name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> anyhow::Result<&Model> {
+        self.models
+            .get(name)
+            .ok_or_else(|| BadRequest(format!("Unknown model '{}'", name)).into())
+    }
+
+    pub fn select(&self, name: Option<&str>, prompt: &Prompt) -> anyhow::Result<&Model> {
+        match name {
+            Some(name) => self.get(name),
+            None => Ok(self.route(prompt)),
+        }
+    }
+
+    pub fn route(&self, prompt: &Prompt) -> &Model {
+        let name = if prompt.is_media() {
+            &self.routing.media
+        } else {
+            &self.routing.text
+        };
+        &self.models[name]
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = self.models.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+}
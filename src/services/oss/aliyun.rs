@@ -0,0 +1,399 @@
+use std::{collections::HashMap, io, sync::Arc};
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use reqwest::{header::HeaderMap, Body, Method, Response, Url};
+use sha2::{Digest, Sha256};
+
+use super::{
+    remote::{check_response, public_endpoint, RemoteStore, UNSIGNED_PAYLOAD},
+    ObjectStore, Reader, Stream,
+};
+use crate::entities::{
+    config::OSSConfig,
+    datetime::DateTime,
+    oss::{parse_http_date, MultipartUpload, ObjectMeta, ObjectSummary},
+};
+
+#[derive(Clone)]
+pub struct AliyunOSS {
+    config: Arc<OSSConfig>,
+    region: Arc<String>,
+}
+
+impl AliyunOSS {
+    pub fn new(config: &OSSConfig) -> Self {
+        let config = config.clone();
+        let pattern = Regex::new(r"oss-(.*?)(-internal)?\.aliyuncs\.com").unwrap();
+        let region = pattern
+            .captures(&config.endpoint)
+            .and_then(|caps| caps.get(1))
+            .unwrap_or_else(|| panic!("Invalid endpoint '{}'", config.endpoint))
+            .as_str()
+            .to_owned();
+        Self {
+            config: Arc::new(config),
+            region: Arc::new(region),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl ObjectStore for AliyunOSS {
+    async fn head(&self, name: &str) -> anyhow::Result<ObjectMeta> {
+        let key = self.build_key(name)?;
+        self.head_object(&key).await
+    }
+
+    async fn get(&self, name: &str) -> anyhow::Result<(Stream<io::Result<Bytes>>, ObjectMeta)> {
+        self.get_object(name).await
+    }
+
+    async fn get_range(
+        &self,
+        name: &str,
+        range: (u64, u64),
+    ) -> anyhow::Result<Stream<io::Result<Bytes>>> {
+        self.get_object_by_range(name, range).await
+    }
+
+    async fn put(&self, name: &str, reader: Reader<'_>, meta: ObjectMeta) -> anyhow::Result<()> {
+        self.put_object(name, reader, meta).await
+    }
+
+    async fn delete(&self, name: &str) -> anyhow::Result<()> {
+        let key = self.build_key(name)?;
+        self.request(
+            &key,
+            Method::DELETE,
+            HashMap::new(),
+            HeaderMap::new(),
+            Body::default(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> anyhow::Result<Vec<ObjectSummary>> {
+        self.list_objects(prefix).await
+    }
+
+    async fn list_uploads(&self) -> anyhow::Result<Vec<MultipartUpload>> {
+        self.list_multipart_uploads().await
+    }
+
+    async fn abort_upload(&self, name: &str, upload_id: &str) -> anyhow::Result<()> {
+        let key = self.build_key(name)?;
+        self.abort_multipart_upload(&key, upload_id).await
+    }
+
+    fn presign_url(&self, method: Method, name: &str, expires: u64) -> anyhow::Result<String> {
+        let key = self.build_key(name)?;
+        let datetime_iso8601 = DateTime::utc().format("%Y%m%dT%H%M%SZ");
+        let sign_date = datetime_iso8601.split_once("T").unwrap().0.to_owned();
+        let mut query = HashMap::new();
+        query.insert(
+            "x-oss-signature-version".to_owned(),
+            "OSS4-HMAC-SHA256".to_owned(),
+        );
+        query.insert(
+            "x-oss-credential".to_owned(),
+            format!(
+                "{}/{}/{}/oss/aliyun_v4_request",
+                self.config.access_key_id, sign_date, self.region
+            ),
+        );
+        query.insert("x-oss-date".to_owned(), datetime_iso8601.clone());
+        query.insert("x-oss-expires".to_owned(), expires.to_string());
+        let signature = self.sign_v4(
+            &key,
+            &method,
+            &query,
+            &HeaderMap::new(),
+            Vec::new(),
+            &datetime_iso8601,
+            &sign_date,
+        )?;
+        query.insert("x-oss-signature".to_owned(), signature);
+        let query = query
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "{}={}",
+                    urlencoding::encode(key),
+                    urlencoding::encode(value)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        Ok(format!(
+            "https://{}.{}{}?{}",
+            self.config.bucket,
+            public_endpoint(&self.config),
+            urlencoding::encode(&key).replace("%2F", "/"),
+            query
+        ))
+    }
+}
+
+#[rocket::async_trait]
+impl RemoteStore for AliyunOSS {
+    fn prefix(&self) -> &str {
+        &self.config.prefix
+    }
+
+    async fn head_object(&self, key: &str) -> anyhow::Result<ObjectMeta> {
+        let response = self
+            .request(
+                key,
+                Method::HEAD,
+                HashMap::new(),
+                HeaderMap::new(),
+                Body::default(),
+            )
+            .await?;
+        let headers = response.headers();
+        let content_type = headers
+            .get("Content-Type")
+            .ok_or_else(|| anyhow!("Missing response header 'Content-Type'"))?
+            .to_str()?
+            .to_owned();
+        let content_length = headers
+            .get("Content-Length")
+            .ok_or_else(|| anyhow!("Missing response header 'Content-Length'"))?
+            .to_str()?
+            .parse()?;
+        let e_tag = match headers.get("ETag") {
+            Some(value) => Some(value.to_str()?.to_owned()),
+            None => None,
+        };
+        let last_modified = match headers.get("Last-Modified") {
+            Some(value) => Some(
+                parse_http_date(value.to_str()?)
+                    .ok_or_else(|| anyhow!("Invalid response header 'Last-Modified'"))?,
+            ),
+            None => None,
+        };
+        Ok(ObjectMeta {
+            content_type,
+            content_length,
+            e_tag,
+            last_modified,
+        })
+    }
+
+    async fn request(
+        &self,
+        key: &str,
+        method: Method,
+        query: HashMap<String, String>,
+        mut headers: HeaderMap,
+        data: Body,
+    ) -> anyhow::Result<Response> {
+        let host = format!("{}.{}", self.config.bucket, self.config.endpoint);
+        let url = Url::parse(&format!(
+            "http://{}{}",
+            host,
+            urlencoding::encode(key).replace("%2F", "/")
+        ))?;
+        let mut additional_headers = headers
+            .keys()
+            .map(|key| key.as_str().to_lowercase())
+            .collect::<Vec<_>>();
+        additional_headers.sort_by(|val1, val2| val1.cmp(val2));
+        headers.insert("Host", host.parse()?);
+        let date = DateTime::utc();
+        headers.insert("Date", date.format("%a, %d %b %Y %H:%M:%S GMT").parse()?);
+        headers.insert("x-oss-date", date.format("%Y%m%dT%H%M%SZ").parse()?);
+        headers.insert("x-oss-content-sha256", UNSIGNED_PAYLOAD.parse()?);
+        let auth = self.authorize_v4(key, &method, &query, &headers, additional_headers)?;
+        headers.insert("Authorization", auth.parse()?);
+        let response = reqwest::Client::new()
+            .request(method, url)
+            .query(&query)
+            .headers(headers)
+            .body(data)
+            .send()
+            .await?;
+        check_response(key, response).await
+    }
+}
+
+impl AliyunOSS {
+    fn authorize_v4(
+        &self,
+        key: &str,
+        method: &Method,
+        query: &HashMap<String, String>,
+        headers: &HeaderMap,
+        additional_headers: Vec<String>,
+    ) -> anyhow::Result<String> {
+        let datetime_iso8601 = headers
+            .get("x-oss-date")
+            .ok_or_else(|| anyhow!("Missing request header 'x-oss-date'"))?
+            .to_str()?
+            .to_owned();
+        let sign_date = datetime_iso8601.split_once("T").unwrap().0.to_owned();
+        let mut auth = format!(
+            "OSS4-HMAC-SHA256 Credential={}/{}/{}/oss/aliyun_v4_request",
+            self.config.access_key_id, sign_date, self.region,
+        );
+        if !additional_headers.is_empty() {
+            auth += &format!(", AdditionalHeaders={}", additional_headers.join(";"));
+        }
+        let signature = self.sign_v4(
+            key,
+            method,
+            query,
+            headers,
+            additional_headers,
+            &datetime_iso8601,
+            &sign_date,
+        )?;
+        auth += &format!(", Signature={}", signature);
+        Ok(auth)
+    }
+
+    fn sign_v4(
+        &self,
+        key: &str,
+        method: &Method,
+        query: &HashMap<String, String>,
+        headers: &HeaderMap,
+        additional_headers: Vec<String>,
+        datetime_iso8601: &str,
+        sign_date: &str,
+    ) -> anyhow::Result<String> {
+        let scope = format!("{}/{}/oss/aliyun_v4_request", sign_date, self.region);
+        let uri =
+            urlencoding::encode(&format!("/{}{}", self.config.bucket, key)).replace("%2F", "/");
+        let canonical_query = if query.is_empty() {
+            String::new()
+        } else {
+            let mut sorted = query.iter().collect::<Vec<_>>();
+            sorted.sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+            sorted
+                .into_iter()
+                .map(|(key, value)| {
+                    if value.is_empty() {
+                        urlencoding::encode(key).to_string()
+                    } else {
+                        format!(
+                            "{}={}",
+                            urlencoding::encode(key),
+                            urlencoding::encode(value)
+                        )
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("&")
+        };
+        let content_sha256 = match headers.get("x-oss-content-sha256") {
+            Some(value) => value.to_str()?,
+            None => UNSIGNED_PAYLOAD,
+        };
+        let canonical_headers = {
+            let mut signed = HashMap::new();
+            let conditional_headers = vec!["content-type", "content-md5"];
+            for (key, value) in headers {
+                let key = key.as_str().to_lowercase();
+                if conditional_headers.contains(&key.as_str())
+                    || key.starts_with("x-oss-")
+                    || additional_headers.contains(&key)
+                {
+                    signed.insert(key, value.to_str()?.trim());
+                }
+            }
+            let mut sorted = signed.into_iter().collect::<Vec<_>>();
+            sorted.sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+            sorted
+                .into_iter()
+                .map(|(key, value)| format!("{}:{}\n", key, value))
+                .collect::<String>()
+        };
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method,
+            uri,
+            canonical_query,
+            canonical_headers,
+            additional_headers.join(";"),
+            content_sha256,
+        );
+        let string_to_sign = format!(
+            "OSS4-HMAC-SHA256\n{}\n{}\n{:x}",
+            datetime_iso8601,
+            scope,
+            Sha256::digest(canonical_request)
+        );
+        let date_key = self.hmac_sha256(
+            format!("aliyun_v4{}", self.config.access_key_secret),
+            sign_date,
+        )?;
+        let date_region_key = self.hmac_sha256(date_key, self.region.as_ref())?;
+        let date_region_service_key = self.hmac_sha256(date_region_key, "oss")?;
+        let signing_key = self.hmac_sha256(date_region_service_key, "aliyun_v4_request")?;
+        let signature = self.hmac_sha256(signing_key, string_to_sign)?;
+        Ok(format!("{:x}", signature))
+    }
+
+    fn hmac_sha256<K: AsRef<[u8]>, T: AsRef<[u8]>>(
+        &self,
+        key: K,
+        data: T,
+    ) -> anyhow::Result<hmac::digest::Output<Sha256>> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_ref())?;
+        mac.update(data.as_ref());
+        Ok(mac.finalize().into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn build_oss() -> AliyunOSS {
+        AliyunOSS {
+            config: Arc::new(OSSConfig {
+                backend: Default::default(),
+                region: None,
+                path_style: false,
+                max_presign_expires: 3600,
+                max_upload_age: 86400,
+                root: None,
+                bucket: "oss-rocket-agentx".to_owned(),
+                endpoint: "oss-cn-hangzhou.aliyuncs.com".to_owned(),
+                public_endpoint: None,
+                access_key_id: "".to_owned(),
+                access_key_secret: "".to_owned(),
+                prefix: "/".to_owned(),
+            }),
+            region: Arc::new("cn-hangzhou".to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_head_object() {
+        let oss = build_oss();
+        let key = oss.build_key("Rust 程序设计语言 简体中文版.pdf").unwrap();
+        let meta = oss.head_object(&key).await.unwrap();
+        println!("{:?}", meta);
+    }
+
+    #[tokio::test]
+    async fn test_get_object() {
+        let oss = build_oss();
+        let (mut stream, meta) = oss
+            .get_object("Rust 程序设计语言 简体中文版.pdf")
+            .await
+            .unwrap();
+        println!("{:?}", meta);
+        while let Some(chunk) = stream.next().await {
+            println!("{}", chunk.unwrap().len());
+        }
+    }
+}
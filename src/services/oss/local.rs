@@ -0,0 +1,238 @@
+use std::{
+    io::{ErrorKind, SeekFrom},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use reqwest::Method;
+use rocket::http::ContentType;
+use tokio::{
+    fs::{self, File},
+    io::{self, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use super::{ObjectStore, Reader, Stream};
+use crate::entities::{
+    config::OSSConfig,
+    oss::{MultipartUpload, ObjectMeta, ObjectSummary},
+    response::{BadRequest, NotFound},
+};
+
+static DEFAULT_ROOT: &str = "data";
+static DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(config: &OSSConfig) -> Self {
+        let root = config.root.as_deref().unwrap_or(DEFAULT_ROOT);
+        Self {
+            root: PathBuf::from(root).join(config.prefix.trim_matches('/')),
+        }
+    }
+
+    fn build_path(&self, name: &str) -> anyhow::Result<PathBuf> {
+        let path = Path::new(name);
+        if name.is_empty()
+            || name.starts_with('.')
+            || path.parent().is_some_and(|p| p != Path::new(""))
+        {
+            return Err(anyhow!("Invalid file name: {}", name));
+        }
+        Ok(self.root.join(name))
+    }
+
+    fn not_found(&self, name: &str, err: io::Error) -> anyhow::Error {
+        match err.kind() {
+            ErrorKind::NotFound => NotFound(format!("File '{}' not existed", name)).into(),
+            _ => err.into(),
+        }
+    }
+
+    fn content_type(&self, name: &str) -> String {
+        Path::new(name)
+            .extension()
+            .and_then(|ext| ContentType::from_extension(ext.to_str()?))
+            .map(|content_type| content_type.to_string())
+            .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_owned())
+    }
+}
+
+#[rocket::async_trait]
+impl ObjectStore for LocalStore {
+    async fn head(&self, name: &str) -> anyhow::Result<ObjectMeta> {
+        let path = self.build_path(name)?;
+        let metadata = fs::metadata(&path)
+            .await
+            .map_err(|err| self.not_found(name, err))?;
+        let last_modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .and_then(|modified| chrono::DateTime::from_timestamp(modified.as_secs() as i64, 0));
+        let e_tag = last_modified.map(|last_modified| {
+            format!("\"{:x}-{:x}\"", last_modified.timestamp(), metadata.len())
+        });
+        Ok(ObjectMeta {
+            content_type: self.content_type(name),
+            content_length: metadata.len(),
+            e_tag,
+            last_modified,
+        })
+    }
+
+    async fn get(&self, name: &str) -> anyhow::Result<(Stream<io::Result<Bytes>>, ObjectMeta)> {
+        let meta = self.head(name).await?;
+        let file = File::open(self.build_path(name)?)
+            .await
+            .map_err(|err| self.not_found(name, err))?;
+        Ok((Box::pin(ReaderStream::new(file)), meta))
+    }
+
+    async fn get_range(
+        &self,
+        name: &str,
+        range: (u64, u64),
+    ) -> anyhow::Result<Stream<io::Result<Bytes>>> {
+        let (start, end) = range;
+        let mut file = File::open(self.build_path(name)?)
+            .await
+            .map_err(|err| self.not_found(name, err))?;
+        file.seek(SeekFrom::Start(start)).await?;
+        Ok(Box::pin(ReaderStream::new(file.take(end - start + 1))))
+    }
+
+    // Files carry no metadata, so the supplied content type is dropped and
+    // `head` infers it from the extension instead.
+    async fn put(&self, name: &str, mut reader: Reader<'_>, _: ObjectMeta) -> anyhow::Result<()> {
+        let path = self.build_path(name)?;
+        fs::create_dir_all(&self.root).await?;
+        // Write to a hidden temporary file first so readers never see partial objects.
+        let temp = self.root.join(format!(".{}.{}", name, Uuid::new_v4()));
+        let result = async {
+            let mut file = File::create(&temp).await?;
+            io::copy(&mut reader, &mut file).await?;
+            file.flush().await?;
+            fs::rename(&temp, &path).await?;
+            anyhow::Ok(())
+        }
+        .await;
+        if result.is_err() {
+            let _ = fs::remove_file(&temp).await;
+        }
+        result
+    }
+
+    async fn delete(&self, name: &str) -> anyhow::Result<()> {
+        fs::remove_file(self.build_path(name)?)
+            .await
+            .map_err(|err| self.not_found(name, err))
+    }
+
+    async fn list(&self, prefix: &str) -> anyhow::Result<Vec<ObjectSummary>> {
+        let mut entries = match fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut objects = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') || !name.starts_with(prefix) {
+                continue;
+            }
+            let metadata = entry.metadata().await?;
+            if metadata.is_file() {
+                objects.push(ObjectSummary {
+                    name,
+                    size: metadata.len(),
+                });
+            }
+        }
+        objects.sort_by(|object1, object2| object1.name.cmp(&object2.name));
+        Ok(objects)
+    }
+
+    async fn list_uploads(&self) -> anyhow::Result<Vec<MultipartUpload>> {
+        Ok(Vec::new())
+    }
+
+    async fn abort_upload(&self, _name: &str, upload_id: &str) -> anyhow::Result<()> {
+        Err(NotFound(format!("Multipart upload '{}' not existed", upload_id)).into())
+    }
+
+    fn presign_url(&self, _method: Method, _name: &str, _expires: u64) -> anyhow::Result<String> {
+        Err(
+            BadRequest("Presigned URLs are not supported by the local storage backend".to_owned())
+                .into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    fn build_store() -> LocalStore {
+        LocalStore {
+            root: std::env::temp_dir().join(format!("rocket-agentx-{}", Uuid::new_v4())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_put_get_object() {
+        let store = build_store();
+        let data = b"hello world".to_vec();
+        let meta = ObjectMeta {
+            content_type: "text/plain".to_owned(),
+            content_length: data.len() as u64,
+            ..Default::default()
+        };
+        store
+            .put("hello.txt", Box::pin(Cursor::new(data.clone())), meta)
+            .await
+            .unwrap();
+        let meta = store.head("hello.txt").await.unwrap();
+        assert_eq!(meta.content_length, data.len() as u64);
+        assert_eq!(meta.content_type, "text/plain; charset=utf-8");
+        assert!(meta.e_tag.is_some() && meta.last_modified.is_some());
+        let (stream, _) = store.get("hello.txt").await.unwrap();
+        let chunks = stream.try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(chunks.concat(), data);
+        let stream = store.get_range("hello.txt", (6, 10)).await.unwrap();
+        let chunks = stream.try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(chunks.concat(), b"world");
+        let objects = store.list("hello").await.unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].name, "hello.txt");
+        store.delete("hello.txt").await.unwrap();
+        assert!(store.head("hello.txt").await.is_err());
+        fs::remove_dir_all(&store.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_object() {
+        let store = build_store();
+        let err = store.head("missing.txt").await.unwrap_err();
+        assert!(err.downcast_ref::<NotFound>().is_some());
+        let err = store.get_range("missing.txt", (0, 1)).await.err().unwrap();
+        assert!(err.downcast_ref::<NotFound>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_name() {
+        let store = build_store();
+        assert!(store.head("../secret").await.is_err());
+        assert!(store.head(".hidden").await.is_err());
+    }
+}
@@ -0,0 +1,148 @@
+mod aliyun;
+mod local;
+mod remote;
+mod s3;
+
+use std::{io, ops::Deref, pin::Pin};
+
+use bytes::Bytes;
+use chrono::{Duration, Local};
+use reqwest::Method;
+use tokio::io::AsyncRead;
+
+use crate::{
+    entities::{
+        config::{ServiceConfig, StorageBackend},
+        datetime::DateTime,
+        oss::{MultipartUpload, ObjectMeta, ObjectSummary, PresignedUrl},
+        response::{BadRequest, NotFound},
+    },
+    services::Inject,
+};
+
+pub static ARCHIVE_PREFIX: &str = "_archive-";
+static MIN_UPLOAD_AGE: u64 = 3600;
+
+pub type Stream<T> = Pin<Box<dyn futures::Stream<Item = T> + Send>>;
+pub type Reader<'a> = Pin<Box<dyn AsyncRead + Send + 'a>>;
+
+#[rocket::async_trait]
+pub trait ObjectStore: Send + Sync {
+    async fn head(&self, name: &str) -> anyhow::Result<ObjectMeta>;
+
+    async fn get(&self, name: &str) -> anyhow::Result<(Stream<io::Result<Bytes>>, ObjectMeta)>;
+
+    async fn get_range(
+        &self,
+        name: &str,
+        range: (u64, u64),
+    ) -> anyhow::Result<Stream<io::Result<Bytes>>>;
+
+    async fn put(&self, name: &str, reader: Reader<'_>, meta: ObjectMeta) -> anyhow::Result<()>;
+
+    async fn delete(&self, name: &str) -> anyhow::Result<()>;
+
+    async fn list(&self, prefix: &str) -> anyhow::Result<Vec<ObjectSummary>>;
+
+    async fn list_uploads(&self) -> anyhow::Result<Vec<MultipartUpload>>;
+
+    async fn abort_upload(&self, name: &str, upload_id: &str) -> anyhow::Result<()>;
+
+    fn presign_url(&self, method: Method, name: &str, expires: u64) -> anyhow::Result<String>;
+}
+
+pub struct Storage {
+    store: Box<dyn ObjectStore>,
+    max_presign_expires: u64,
+    max_upload_age: u64,
+}
+
+impl Inject for Storage {
+    fn new(config: &ServiceConfig) -> Self {
+        let store: Box<dyn ObjectStore> = match config.oss.backend {
+            StorageBackend::Aliyun => Box::new(aliyun::AliyunOSS::new(&config.oss)),
+            StorageBackend::Local => Box::new(local::LocalStore::new(&config.oss)),
+            StorageBackend::S3 => Box::new(s3::S3Store::new(&config.oss)),
+        };
+        Self {
+            store,
+            max_presign_expires: config.oss.max_presign_expires,
+            max_upload_age: config.oss.max_upload_age,
+        }
+    }
+}
+
+impl Storage {
+    pub fn check_public(&self, name: &str) -> anyhow::Result<()> {
+        // Internal objects are never reachable through the file routes.
+        if name.starts_with(ARCHIVE_PREFIX) {
+            return Err(NotFound(format!("File '{name}' not existed")).into());
+        }
+        Ok(())
+    }
+
+    pub fn presign(
+        &self,
+        method: Method,
+        name: &str,
+        expires: Option<u64>,
+    ) -> anyhow::Result<PresignedUrl> {
+        let expires = expires.unwrap_or(self.max_presign_expires);
+        if expires == 0 || expires > self.max_presign_expires {
+            return Err(BadRequest(format!(
+                "Expires must be between 1 and {} seconds",
+                self.max_presign_expires
+            ))
+            .into());
+        }
+        let url = self.store.presign_url(method.clone(), name, expires)?;
+        Ok(PresignedUrl {
+            name: name.to_owned(),
+            method: method.to_string(),
+            url,
+            expire_time: DateTime::from(Local::now() + Duration::seconds(expires as i64)),
+        })
+    }
+
+    pub async fn abort_stale_uploads(
+        &self,
+        max_age: Option<u64>,
+    ) -> anyhow::Result<Vec<MultipartUpload>> {
+        let max_age = max_age.unwrap_or(self.max_upload_age);
+        // Younger uploads may still be in flight
+        if max_age < MIN_UPLOAD_AGE {
+            return Err(BadRequest(format!(
+                "Max age must be at least {} seconds",
+                MIN_UPLOAD_AGE
+            ))
+            .into());
+        }
+        let deadline = Local::now() - Duration::seconds(max_age as i64);
+        let mut aborted = Vec::new();
+        for upload in self.store.list_uploads().await? {
+            if *upload.initiated > deadline {
+                continue;
+            }
+            match self
+                .store
+                .abort_upload(&upload.name, &upload.upload_id)
+                .await
+            {
+                Ok(()) => aborted.push(upload),
+                Err(err) => eprintln!(
+                    "Failed to abort multipart upload '{}' of '{}': {:?}",
+                    upload.upload_id, upload.name, err
+                ),
+            }
+        }
+        Ok(aborted)
+    }
+}
+
+impl Deref for Storage {
+    type Target = dyn ObjectStore;
+
+    fn deref(&self) -> &Self::Target {
+        self.store.as_ref()
+    }
+}
@@ -0,0 +1,542 @@
+use std::{
+    collections::HashMap, io, mem, ops::Range, path::Path, str::from_utf8, sync::Arc,
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use async_stream::stream;
+use bytes::{Bytes, BytesMut};
+use chrono::Local;
+use futures::StreamExt;
+use quick_xml::events::{BytesStart, Event};
+use reqwest::{header::HeaderMap, Body, Method, Response, StatusCode};
+use serde::Serialize;
+use tokio::{io::AsyncReadExt, sync::Semaphore, task::JoinSet, time::sleep};
+use tokio_util::io::ReaderStream;
+
+use super::{Reader, Stream};
+use crate::entities::{
+    config::OSSConfig,
+    datetime::DateTime,
+    oss::{MultipartUpload, ObjectMeta, ObjectSummary},
+    response::NotFound,
+};
+
+pub static GET_OBJECT_RANGE_SIZE: usize = 16 * 1024 * 1024; // 16MB
+pub static MULTIPART_UPLOAD_THRESHOLD: usize = 16 * 1024 * 1024; // 16MB
+pub static MULTIPART_UPLOAD_PART_SIZE: usize = 8 * 1024 * 1024; // 8MB, S3 rejects parts under 5MB
+pub static MULTIPART_UPLOAD_WORKERS_NUM: usize = 3;
+pub static UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MultipartUploadResult {
+    part_number: u16,
+    e_tag: String,
+}
+
+pub fn public_endpoint(config: &OSSConfig) -> &str {
+    // Presigned URLs go to clients, which may not reach the internal endpoint
+    config
+        .public_endpoint
+        .as_deref()
+        .unwrap_or(&config.endpoint)
+}
+
+pub async fn check_response(key: &str, response: Response) -> anyhow::Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    if status == StatusCode::NOT_FOUND {
+        return Err(NotFound(format!("Object '{}' not existed", key)).into());
+    }
+    Err(anyhow!(
+        "Request failed ({}): {}",
+        status,
+        response.text().await?
+    ))
+}
+
+struct PendingUpload<S: RemoteStore> {
+    store: S,
+    key: String,
+    upload_id: String,
+    done: bool,
+}
+
+impl<S: RemoteStore> Drop for PendingUpload<S> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        // The upload future was dropped midway, e.g. when the client disconnected
+        let store = self.store.clone();
+        let key = mem::take(&mut self.key);
+        let upload_id = mem::take(&mut self.upload_id);
+        tokio::spawn(async move {
+            if let Err(err) = store.abort_multipart_upload(&key, &upload_id).await {
+                eprintln!(
+                    "Failed to abort multipart upload '{}' of '{}': {:?}",
+                    upload_id, key, err
+                );
+            }
+        });
+    }
+}
+
+#[rocket::async_trait]
+pub trait RemoteStore: Clone + Send + Sync + 'static {
+    fn prefix(&self) -> &str;
+
+    async fn head_object(&self, key: &str) -> anyhow::Result<ObjectMeta>;
+
+    async fn request(
+        &self,
+        key: &str,
+        method: Method,
+        query: HashMap<String, String>,
+        headers: HeaderMap,
+        data: Body,
+    ) -> anyhow::Result<Response>;
+
+    async fn get_object(
+        &self,
+        name: &str,
+    ) -> anyhow::Result<(Stream<io::Result<Bytes>>, ObjectMeta)> {
+        let key = self.build_key(name)?;
+        let meta = self.head_object(&key).await?;
+        let stream = self.stream_object(key, 0..meta.content_length);
+        Ok((stream, meta))
+    }
+
+    async fn get_object_by_range(
+        &self,
+        name: &str,
+        range: (u64, u64),
+    ) -> anyhow::Result<Stream<io::Result<Bytes>>> {
+        let key = self.build_key(name)?;
+        let (start, end) = range;
+        Ok(self.stream_object(key, start..end + 1))
+    }
+
+    fn stream_object(&self, key: String, range: Range<u64>) -> Stream<io::Result<Bytes>> {
+        let self_cloned = self.clone();
+        let stream = stream! {
+            'outer: for start in range.clone().step_by(GET_OBJECT_RANGE_SIZE) {
+                let end = (start + GET_OBJECT_RANGE_SIZE as u64 - 1).min(range.end - 1);
+                let mut offset = start;
+                let mut retry = 0;
+                loop {
+                    // Resume from the last yielded byte so a retry never duplicates data
+                    let err = match self_cloned.get_object_range(&key, (offset, end)).await {
+                        Ok(mut stream) => loop {
+                            match stream.next().await {
+                                Some(Ok(chunk)) => {
+                                    offset += chunk.len() as u64;
+                                    yield Ok(chunk);
+                                }
+                                _ if offset > end => break None,
+                                Some(Err(err)) => break Some(anyhow::Error::from(err)),
+                                None => {
+                                    break Some(anyhow!("Object '{key}' ended before byte {offset}"));
+                                }
+                            }
+                        },
+                        Err(err) => Some(err),
+                    };
+                    let Some(err) = err else {
+                        break;
+                    };
+                    if retry == 3 {
+                        yield Err(io::Error::other(err));
+                        break 'outer;
+                    }
+                    retry += 1;
+                    sleep(Duration::from_secs(retry)).await;
+                }
+            }
+        };
+        Box::pin(stream)
+    }
+
+    async fn get_object_range(
+        &self,
+        key: &str,
+        range: (u64, u64),
+    ) -> anyhow::Result<Stream<Result<Bytes, reqwest::Error>>> {
+        let mut headers = HeaderMap::new();
+        let (start, end) = range;
+        headers.insert("Range", format!("bytes={}-{}", start, end).parse()?);
+        let response = self
+            .request(key, Method::GET, HashMap::new(), headers, Body::default())
+            .await?;
+        Ok(Box::pin(response.bytes_stream()))
+    }
+
+    async fn put_object(
+        &self,
+        name: &str,
+        reader: Reader<'_>,
+        meta: ObjectMeta,
+    ) -> anyhow::Result<()> {
+        let key = self.build_key(name)?;
+        let ObjectMeta { content_type, .. } = meta;
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", content_type.parse()?);
+        let content_disposition = format!("attachment; filename=\"{}\"", urlencoding::encode(name));
+        headers.insert("Content-Disposition", content_disposition.parse()?);
+        if meta.content_length <= MULTIPART_UPLOAD_THRESHOLD as u64 {
+            self.put_object_by_key(&key, reader, headers).await?;
+        } else {
+            self.multipart_upload(&key, reader, headers).await?;
+        }
+        Ok(())
+    }
+
+    async fn put_object_by_key(
+        &self,
+        key: &str,
+        reader: Reader<'_>,
+        headers: HeaderMap,
+    ) -> anyhow::Result<()> {
+        let mut bytes = Vec::new();
+        reader
+            .take(MULTIPART_UPLOAD_THRESHOLD as u64)
+            .read_to_end(&mut bytes)
+            .await?;
+        self.request(key, Method::PUT, HashMap::new(), headers, bytes.into())
+            .await?;
+        Ok(())
+    }
+
+    async fn multipart_upload(
+        &self,
+        key: &str,
+        reader: Reader<'_>,
+        headers: HeaderMap,
+    ) -> anyhow::Result<()> {
+        let upload_id = self.initiate_multipart_upload(key, headers).await?;
+        // Declared before the set so in-flight parts are dropped before the abort is spawned
+        let mut pending = PendingUpload {
+            store: self.clone(),
+            key: key.to_owned(),
+            upload_id: upload_id.clone(),
+            done: false,
+        };
+        let mut set = JoinSet::new();
+        let result = async {
+            let semaphore = Arc::new(Semaphore::new(MULTIPART_UPLOAD_WORKERS_NUM));
+            let mut spawn = |part: Bytes, part_number: u16| {
+                let self_cloned = self.clone();
+                let key_owned = key.to_owned();
+                let upload_id_clone = upload_id.clone();
+                let semaphore_cloned = semaphore.clone();
+                set.spawn(async move {
+                    let permit = semaphore_cloned.acquire().await?;
+                    let result = self_cloned
+                        .upload_part(&key_owned, part, &upload_id_clone, part_number)
+                        .await?;
+                    drop(permit);
+                    anyhow::Ok(result)
+                });
+            };
+            let mut stream = ReaderStream::new(reader);
+            let mut buffer = BytesMut::new();
+            let mut part_number = 1;
+            while let Some(chunk) = stream.next().await {
+                buffer.extend(chunk?);
+                while buffer.len() >= MULTIPART_UPLOAD_PART_SIZE {
+                    let part = buffer.split_to(MULTIPART_UPLOAD_PART_SIZE).freeze();
+                    spawn(part, part_number);
+                    part_number += 1;
+                }
+            }
+            if !buffer.is_empty() {
+                spawn(buffer.freeze(), part_number);
+            }
+            let mut parts = Vec::new();
+            while let Some(result) = set.join_next().await {
+                parts.push(result??);
+            }
+            parts.sort_by_key(|part| part.part_number);
+            self.complete_multipart_upload(key, &upload_id, parts).await
+        }
+        .await;
+        if result.is_err() {
+            // Stop in-flight parts first so none of them lands after the abort
+            set.shutdown().await;
+            if let Err(err) = self.abort_multipart_upload(key, &upload_id).await {
+                eprintln!(
+                    "Failed to abort multipart upload '{}' of '{}': {:?}",
+                    upload_id, key, err
+                );
+            }
+        }
+        pending.done = true;
+        result
+    }
+
+    async fn initiate_multipart_upload(
+        &self,
+        key: &str,
+        headers: HeaderMap,
+    ) -> anyhow::Result<String> {
+        let mut query = HashMap::new();
+        query.insert("uploads".to_owned(), "".to_owned());
+        let response = self
+            .request(key, Method::POST, query, headers, Body::default())
+            .await?;
+        let xml = response.text().await?;
+        let mut reader = quick_xml::Reader::from_str(&xml);
+        reader.config_mut().trim_text(true);
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) => {
+                    if e.name().as_ref() == b"UploadId" {
+                        break;
+                    }
+                }
+                Event::Eof => return Err(anyhow!("Missing element 'UploadId'")),
+                _ => (),
+            }
+        }
+        if let Event::Text(text) = reader.read_event()? {
+            Ok(text.decode()?.into_owned())
+        } else {
+            Err(anyhow!("Failed to parse 'UploadId'"))
+        }
+    }
+
+    async fn upload_part(
+        &self,
+        key: &str,
+        data: Bytes,
+        upload_id: &str,
+        part_number: u16,
+    ) -> anyhow::Result<MultipartUploadResult> {
+        let mut query = HashMap::new();
+        query.insert("uploadId".to_owned(), upload_id.to_owned());
+        query.insert("partNumber".to_owned(), part_number.to_string());
+        for retry in 0..=3 {
+            match self
+                .request(
+                    key,
+                    Method::PUT,
+                    query.clone(),
+                    HeaderMap::new(),
+                    data.clone().into(),
+                )
+                .await
+            {
+                Ok(response) => {
+                    let e_tag = response
+                        .headers()
+                        .get("ETag")
+                        .ok_or_else(|| anyhow!("Missing response header 'ETag'"))?
+                        .to_str()?
+                        .to_owned();
+                    return Ok(MultipartUploadResult { part_number, e_tag });
+                }
+                Err(err) => {
+                    if retry < 3 {
+                        sleep(Duration::from_secs(retry + 1)).await;
+                    } else {
+                        return Err(anyhow!(
+                            "Failed to upload part (part_number={}) after {} retries: {:#}",
+                            part_number,
+                            retry,
+                            err
+                        ));
+                    }
+                }
+            }
+        }
+        unreachable!()
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<MultipartUploadResult>,
+    ) -> anyhow::Result<()> {
+        let mut query = HashMap::new();
+        query.insert("uploadId".to_owned(), upload_id.to_owned());
+        let content = {
+            let mut buffer = Vec::new();
+            let mut writer = quick_xml::Writer::new_with_indent(&mut buffer, b' ', 4);
+            let start = BytesStart::new("CompleteMultipartUpload");
+            writer.write_event(Event::Start(start.clone()))?;
+            for part in parts {
+                writer.write_serializable("Part", &part)?;
+            }
+            writer.write_event(Event::End(start.to_end()))?;
+            from_utf8(&buffer)?.to_owned()
+        };
+        let response = self
+            .request(key, Method::POST, query, HeaderMap::new(), content.into())
+            .await?;
+        // S3 may report a failed completion with a 200 status and an error body
+        let xml = response.text().await?;
+        if xml.contains("<Error>") {
+            return Err(anyhow!("Failed to complete multipart upload: {}", xml));
+        }
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> anyhow::Result<()> {
+        let mut query = HashMap::new();
+        query.insert("uploadId".to_owned(), upload_id.to_owned());
+        self.request(
+            key,
+            Method::DELETE,
+            query,
+            HeaderMap::new(),
+            Body::default(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn list_multipart_uploads(&self) -> anyhow::Result<Vec<MultipartUpload>> {
+        let root = self.prefix().trim_matches('/');
+        let key_prefix = if root.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", root)
+        };
+        let mut uploads = Vec::new();
+        let mut markers = None;
+        loop {
+            let mut query = HashMap::new();
+            query.insert("uploads".to_owned(), "".to_owned());
+            query.insert("prefix".to_owned(), key_prefix.clone());
+            query.insert("delimiter".to_owned(), "/".to_owned());
+            if let Some((key_marker, upload_id_marker)) = markers.take() {
+                query.insert("key-marker".to_owned(), key_marker);
+                query.insert("upload-id-marker".to_owned(), upload_id_marker);
+            }
+            let response = self
+                .request("/", Method::GET, query, HeaderMap::new(), Body::default())
+                .await?;
+            let xml = response.text().await?;
+            let mut reader = quick_xml::Reader::from_str(&xml);
+            reader.config_mut().trim_text(true);
+            let mut element = Vec::new();
+            let (mut key, mut upload_id) = (None, None);
+            let (mut next_key_marker, mut next_upload_id_marker) = (None, None);
+            let mut truncated = false;
+            loop {
+                match reader.read_event()? {
+                    Event::Start(e) => element = e.name().as_ref().to_vec(),
+                    Event::Text(text) => {
+                        let text = text.decode()?.into_owned();
+                        match element.as_slice() {
+                            b"Key" => key = Some(text),
+                            b"UploadId" => upload_id = Some(text),
+                            b"Initiated" => {
+                                if let (Some(key), Some(upload_id)) = (key.take(), upload_id.take())
+                                {
+                                    let initiated = chrono::DateTime::parse_from_rfc3339(&text)?
+                                        .with_timezone(&Local);
+                                    uploads.push(MultipartUpload {
+                                        name: key[root.len()..].trim_start_matches('/').to_owned(),
+                                        upload_id,
+                                        initiated: DateTime::from(initiated),
+                                    });
+                                }
+                            }
+                            b"NextKeyMarker" => next_key_marker = Some(text),
+                            b"NextUploadIdMarker" => next_upload_id_marker = Some(text),
+                            b"IsTruncated" => truncated = text == "true",
+                            _ => (),
+                        }
+                    }
+                    Event::End(_) => element.clear(),
+                    Event::Eof => break,
+                    _ => (),
+                }
+            }
+            match (truncated, next_key_marker, next_upload_id_marker) {
+                (true, Some(key_marker), Some(upload_id_marker)) => {
+                    markers = Some((key_marker, upload_id_marker))
+                }
+                _ => break,
+            }
+        }
+        Ok(uploads)
+    }
+
+    async fn list_objects(&self, prefix: &str) -> anyhow::Result<Vec<ObjectSummary>> {
+        if prefix.contains('/') {
+            return Err(anyhow!("Invalid prefix: {}", prefix));
+        }
+        let root = self.prefix().trim_matches('/');
+        let key_prefix = if root.is_empty() {
+            prefix.to_owned()
+        } else {
+            format!("{}/{}", root, prefix)
+        };
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let mut query = HashMap::new();
+            query.insert("list-type".to_owned(), "2".to_owned());
+            query.insert("prefix".to_owned(), key_prefix.clone());
+            query.insert("delimiter".to_owned(), "/".to_owned());
+            if let Some(token) = continuation_token.take() {
+                query.insert("continuation-token".to_owned(), token);
+            }
+            let response = self
+                .request("/", Method::GET, query, HeaderMap::new(), Body::default())
+                .await?;
+            let xml = response.text().await?;
+            let mut reader = quick_xml::Reader::from_str(&xml);
+            reader.config_mut().trim_text(true);
+            let mut element = Vec::new();
+            let mut key = None;
+            loop {
+                match reader.read_event()? {
+                    Event::Start(e) => element = e.name().as_ref().to_vec(),
+                    Event::Text(text) => {
+                        let text = text.decode()?.into_owned();
+                        match element.as_slice() {
+                            b"Key" => key = Some(text),
+                            b"Size" => {
+                                if let Some(key) = key.take() {
+                                    let name = key[root.len()..].trim_start_matches('/');
+                                    objects.push(ObjectSummary {
+                                        name: name.to_owned(),
+                                        size: text.parse()?,
+                                    });
+                                }
+                            }
+                            b"NextContinuationToken" => continuation_token = Some(text),
+                            _ => (),
+                        }
+                    }
+                    Event::End(_) => element.clear(),
+                    Event::Eof => break,
+                    _ => (),
+                }
+            }
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+        Ok(objects)
+    }
+
+    fn build_key(&self, name: &str) -> anyhow::Result<String> {
+        if Path::new(name).parent().is_some_and(|p| p != Path::new("")) {
+            return Err(anyhow!("Invalid file name: {}", name));
+        }
+        let path = Path::new("/").join(self.prefix()).join(name);
+        let key = path
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid file path: {}", path.display()))?
+            .to_owned();
+        Ok(key)
+    }
+}
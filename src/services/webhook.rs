@@ -0,0 +1,338 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use hmac::{Hmac, Mac};
+use reqwest::{redirect::Policy, Url};
+use rocket::fairing::AdHoc;
+use rocket_db_pools::{
+    deadpool_redis::redis::{self, AsyncCommands},
+    Connection,
+};
+use sha2::Sha256;
+use tokio::{net::lookup_host, time::sleep};
+
+use crate::{
+    databases::Tasks,
+    entities::{
+        config::{ServiceConfig, WebhookConfig},
+        datetime::DateTime,
+        response::BadRequest,
+        task::{Status, Task},
+    },
+    services::{executor::Executor, Inject, Service},
+};
+
+#[derive(Clone)]
+pub struct Webhook {
+    config: Arc<WebhookConfig>,
+}
+
+impl Inject for Webhook {
+    fn new(config: &ServiceConfig) -> Self {
+        Self {
+            config: Arc::new(config.webhook.clone()),
+        }
+    }
+}
+
+static WEBHOOK_PREFIX: &str = "WEBHOOK:";
+static LEASE_PREFIX: &str = "WEBHOOK_LEASE:";
+static MAX_BACKOFF: u64 = 60;
+static SCAN_COUNT: usize = 100;
+
+impl Webhook {
+    pub fn resume() -> AdHoc {
+        AdHoc::on_liftoff("Webhook Recovery", |rocket| {
+            Box::pin(async move {
+                if let Err(err) = super::configure(rocket) {
+                    eprintln!("Failed to resume webhooks: {:?}", err);
+                    return;
+                }
+                let webhook = Service::<Webhook>::inject();
+                tokio::spawn(async move {
+                    if let Err(err) = webhook.recover().await {
+                        eprintln!("Failed to resume webhooks: {:?}", err);
+                    }
+                });
+            })
+        })
+    }
+
+    async fn recover(&self) -> anyhow::Result<()> {
+        let mut conn = Tasks::connect().await?;
+        let executor = Service::<Executor>::inject();
+        let mut cursor = 0u64;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(format!("{WEBHOOK_PREFIX}*"))
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query_async(&mut *conn)
+                .await?;
+            for key in keys {
+                let status: Option<String> = conn.hget(&key, "status").await?;
+                if !matches!(status.as_deref(), Some("pending" | "retrying")) {
+                    continue;
+                }
+                let task_id = &key[WEBHOOK_PREFIX.len()..];
+                // Deliveries of unfinished tasks are dispatched when the task completes.
+                let task = match executor.get(&mut conn, task_id).await {
+                    Ok(Some(task))
+                        if task.status.is_completed() && task.status != Status::Cancelled =>
+                    {
+                        task
+                    }
+                    Ok(_) => continue,
+                    Err(err) => {
+                        eprintln!("Failed to read task '{task_id}': {:?}", err);
+                        continue;
+                    }
+                };
+                // Instances starting together scan the same keys, only the lease holder resends.
+                let leased: Option<String> = redis::cmd("SET")
+                    .arg(format!("{LEASE_PREFIX}{task_id}"))
+                    .arg(1)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(self.lease())
+                    .query_async(&mut *conn)
+                    .await?;
+                if leased.is_some() {
+                    self.dispatch(task);
+                }
+            }
+            if next == 0 {
+                return Ok(());
+            }
+            cursor = next;
+        }
+    }
+
+    fn lease(&self) -> u64 {
+        // Long enough to cover every attempt of a delivery and the backoff between them.
+        (self.config.max_retries as u64 + 1) * (self.config.timeout + MAX_BACKOFF)
+    }
+
+    pub async fn register(
+        &self,
+        conn: &mut Connection<Tasks>,
+        task: &Task,
+        url: &str,
+        secret: Option<&str>,
+    ) -> anyhow::Result<()> {
+        self.check(url).await?;
+        let key = format!("{WEBHOOK_PREFIX}{}", task.id);
+        let mut fields = vec![("url", url), ("status", "pending"), ("attempts", "0")];
+        if let Some(secret) = secret {
+            fields.push(("secret", secret));
+        }
+        let _: () = conn.hset_multiple(&key, &fields).await?;
+        if let Some(ttl) = Service::<Executor>::inject().retention(task) {
+            let _: () = conn.expire(&key, ttl as i64).await?;
+        }
+        Ok(())
+    }
+
+    pub fn dispatch(&self, task: Task) {
+        let webhook = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = webhook.deliver(&task).await {
+                eprintln!(
+                    "Failed to deliver callback of task '{}': {:?}",
+                    task.id, err
+                );
+            }
+        });
+    }
+
+    async fn deliver(&self, task: &Task) -> anyhow::Result<()> {
+        let mut conn = Tasks::connect().await?;
+        let key = format!("{WEBHOOK_PREFIX}{}", task.id);
+        let (url, secret, attempts): (Option<String>, Option<String>, Option<u32>) =
+            conn.hget(&key, &["url", "secret", "attempts"][..]).await?;
+        let url = url.ok_or_else(|| anyhow!("Webhook '{}' not existed", task.id))?;
+        let body = serde_json::to_vec(task)?;
+        let mut attempts = attempts.unwrap_or_default();
+        loop {
+            attempts += 1;
+            let attempts_encoded = attempts.to_string();
+            match self.post(&url, &task.id, &body, secret.as_deref()).await {
+                Ok(()) => {
+                    let _: () = conn
+                        .hset_multiple(
+                            &key,
+                            &[
+                                ("status", "delivered"),
+                                ("attempts", attempts_encoded.as_str()),
+                            ],
+                        )
+                        .await?;
+                    return Ok(());
+                }
+                Err(err) => {
+                    let last_error = format!("{:#}", err);
+                    let status = if attempts > self.config.max_retries {
+                        "failed"
+                    } else {
+                        "retrying"
+                    };
+                    let _: () = conn
+                        .hset_multiple(
+                            &key,
+                            &[
+                                ("status", status),
+                                ("attempts", attempts_encoded.as_str()),
+                                ("last_error", last_error.as_str()),
+                            ],
+                        )
+                        .await?;
+                    if attempts > self.config.max_retries {
+                        return Err(err);
+                    }
+                    sleep(Duration::from_secs((1 << attempts.min(6)).min(MAX_BACKOFF))).await;
+                }
+            }
+        }
+    }
+
+    async fn post(
+        &self,
+        url: &str,
+        task_id: &str,
+        body: &[u8],
+        secret: Option<&str>,
+    ) -> anyhow::Result<()> {
+        // Resolve again on every delivery, the host may have been re-pointed since registration.
+        let addrs = self.check(url).await?;
+        let mut builder = reqwest::Client::builder().redirect(Policy::none());
+        if let Some(domain) = Url::parse(url)?.domain().filter(|_| !addrs.is_empty()) {
+            // Connect to the addresses just checked, a second lookup could return other ones.
+            builder = builder.resolve_to_addrs(domain, &addrs);
+        }
+        let timestamp = DateTime::local().timestamp().to_string();
+        let mut request = builder
+            .build()?
+            .post(url)
+            .timeout(Duration::from_secs(self.config.timeout))
+            .header("Content-Type", "application/json")
+            .header("X-Task-Id", task_id)
+            .header("X-Timestamp", &timestamp);
+        if let Some(secret) = secret {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+            mac.update(timestamp.as_bytes());
+            mac.update(b".");
+            mac.update(body);
+            request = request.header(
+                "X-Signature-256",
+                format!("sha256={:x}", mac.finalize().into_bytes()),
+            );
+        }
+        let response = request.body(body.to_vec()).send().await?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Callback failed ({}): {}",
+                response.status(),
+                response.text().await?
+            ))
+        }
+    }
+
+    async fn check(&self, url: &str) -> anyhow::Result<Vec<SocketAddr>> {
+        let parsed = Url::parse(url)
+            .map_err(|err| BadRequest(format!("Invalid callback url '{}': {}", url, err)))?;
+        if parsed.scheme() != "http" && parsed.scheme() != "https" {
+            return Err(BadRequest(format!("Invalid callback url '{}'", url)).into());
+        }
+        if self.config.allow_private {
+            return Ok(Vec::new());
+        }
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| BadRequest(format!("Invalid callback url '{}'", url)))?;
+        let port = parsed.port_or_known_default().unwrap_or(80);
+        let addrs = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+            Ok(ip) => vec![SocketAddr::new(ip, port)],
+            Err(_) => lookup_host((host, port))
+                .await
+                .map_err(|err| BadRequest(format!("Invalid callback url '{}': {}", url, err)))?
+                .collect(),
+        };
+        if addrs.is_empty() || !addrs.iter().all(|addr| is_public(addr.ip())) {
+            return Err(BadRequest(format!(
+                "Callback url '{}' points at a private address",
+                url
+            ))
+            .into());
+        }
+        Ok(addrs)
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)))
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segment = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || segment & 0xfe00 == 0xfc00
+        || segment & 0xffc0 == 0xfe80)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_public;
+
+    #[test]
+    fn test_is_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["8.8.8.8", "100.128.0.1", "2606:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}